@@ -0,0 +1,284 @@
+use std::{
+    error::Error,
+    fs::{self, DirBuilder},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+const APP_DIR: &str = "cultura";
+
+// The directories used by cultura, they follow the XDG Base Directory
+// specification unless CULTURA_HOME is defined, in which case everything is
+// stored in this directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Locations {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub state_dir: PathBuf,
+    pub runtime_dir: PathBuf,
+    // Before the XDG support everything was stored in ~/.config/cultura
+    legacy_dir: Option<PathBuf>,
+}
+
+impl Locations {
+    #[cfg(test)]
+    pub fn new(home: &Path) -> Locations {
+        resolve(Some(home.to_path_buf()), |_| None).unwrap()
+    }
+
+    pub fn from_env(home: Option<PathBuf>) -> Result<Locations, Box<dyn Error>> {
+        resolve(home, |name| std::env::var(name).ok())
+    }
+
+    fn get_dirs(&self) -> Vec<&PathBuf> {
+        let mut dirs = vec![
+            &self.config_dir,
+            &self.data_dir,
+            &self.state_dir,
+            &self.runtime_dir,
+        ];
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    pub fn create_dirs(&self) -> Result<(), Box<dyn Error>> {
+        for dir in self.get_dirs() {
+            DirBuilder::new().recursive(true).create(dir)?;
+        }
+        Ok(())
+    }
+
+    // Remove the files of cultura then the directories left empty, a
+    // directory like CULTURA_HOME can hold other files which are kept.
+    pub fn remove_files(&self, files: &[(&str, &Path)]) -> Result<(), Box<dyn Error>> {
+        for (name, dir) in files {
+            match fs::remove_file(dir.join(name)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e)?,
+                _ => (),
+            }
+        }
+        for dir in self.get_dirs() {
+            // Only succeeds when the directory is empty
+            let _ = fs::remove_dir(dir);
+        }
+        Ok(())
+    }
+
+    // Move the files found in the legacy directory to their new location, a
+    // file already present at the new location is never overwritten.
+    pub fn migrate(&self, files: &[(&str, &Path)]) -> Result<(), Box<dyn Error>> {
+        let legacy_dir = match &self.legacy_dir {
+            Some(legacy_dir) if legacy_dir.is_dir() => legacy_dir,
+            _ => return Ok(()),
+        };
+        for (name, dir) in files {
+            let source = legacy_dir.join(name);
+            let target = dir.join(name);
+            if source == target || !source.exists() || target.exists() {
+                continue;
+            }
+            DirBuilder::new().recursive(true).create(dir)?;
+            if fs::rename(&source, &target).is_err() {
+                // The directories can be on different filesystems
+                fs::copy(&source, &target)?;
+                fs::remove_file(&source)?;
+            }
+        }
+        // Only succeeds when the legacy directory is empty
+        if !self.get_dirs().contains(&legacy_dir) {
+            let _ = fs::remove_dir(legacy_dir);
+        }
+        Ok(())
+    }
+}
+
+fn resolve(
+    home: Option<PathBuf>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Locations, Box<dyn Error>> {
+    // Relative paths must be ignored according to the specification
+    let get_dir = |name: &str| {
+        var(name)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .map(|p| p.join(APP_DIR))
+    };
+    let legacy_dir = home.as_ref().map(|h| h.join(".config").join(APP_DIR));
+
+    if let Some(dir) = var("CULTURA_HOME").filter(|d| !d.is_empty()) {
+        let dir = PathBuf::from(dir);
+        return Ok(Locations {
+            config_dir: dir.clone(),
+            data_dir: dir.clone(),
+            state_dir: dir.clone(),
+            runtime_dir: dir,
+            legacy_dir,
+        });
+    }
+
+    let home = match home {
+        Some(home) => home,
+        None => Err("path cannot be found".to_string())?,
+    };
+    let state_dir =
+        get_dir("XDG_STATE_HOME").unwrap_or(home.join(".local").join("state").join(APP_DIR));
+    Ok(Locations {
+        config_dir: get_dir("XDG_CONFIG_HOME").unwrap_or(home.join(".config").join(APP_DIR)),
+        data_dir: get_dir("XDG_DATA_HOME")
+            .unwrap_or(home.join(".local").join("share").join(APP_DIR)),
+        // There is no default runtime directory in the specification
+        runtime_dir: get_dir("XDG_RUNTIME_DIR").unwrap_or(state_dir.clone()),
+        state_dir,
+        legacy_dir,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn resolve_with(home: Option<&str>, vars: &[(&str, &str)]) -> Locations {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<String, String>>();
+        resolve(home.map(PathBuf::from), |name| vars.get(name).cloned()).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let l = resolve_with(Some("/home/user"), &[]);
+        assert_eq!(l.config_dir, PathBuf::from("/home/user/.config/cultura"));
+        assert_eq!(l.data_dir, PathBuf::from("/home/user/.local/share/cultura"));
+        assert_eq!(
+            l.state_dir,
+            PathBuf::from("/home/user/.local/state/cultura")
+        );
+        assert_eq!(
+            l.runtime_dir,
+            PathBuf::from("/home/user/.local/state/cultura")
+        );
+
+        let l = resolve_with(
+            Some("/home/user"),
+            &[
+                ("XDG_CONFIG_HOME", "/xdg/config"),
+                ("XDG_DATA_HOME", "/xdg/data"),
+                ("XDG_STATE_HOME", "relative/state"),
+                ("XDG_RUNTIME_DIR", "/run/user/1000"),
+            ],
+        );
+        assert_eq!(l.config_dir, PathBuf::from("/xdg/config/cultura"));
+        assert_eq!(l.data_dir, PathBuf::from("/xdg/data/cultura"));
+        assert_eq!(
+            l.state_dir,
+            PathBuf::from("/home/user/.local/state/cultura")
+        );
+        assert_eq!(l.runtime_dir, PathBuf::from("/run/user/1000/cultura"));
+
+        let l = resolve_with(
+            None,
+            &[
+                ("CULTURA_HOME", "/opt/cultura"),
+                ("XDG_DATA_HOME", "/xdg/data"),
+            ],
+        );
+        assert_eq!(l.config_dir, PathBuf::from("/opt/cultura"));
+        assert_eq!(l.data_dir, PathBuf::from("/opt/cultura"));
+        assert_eq!(l.state_dir, PathBuf::from("/opt/cultura"));
+        assert_eq!(l.runtime_dir, PathBuf::from("/opt/cultura"));
+
+        match resolve(None, |_| None) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "path cannot be found"),
+        }
+    }
+
+    #[test]
+    fn test_migrate() {
+        let home = tempdir().unwrap();
+        let legacy_dir = home.path().join(".config/cultura");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("config.toml"), "config").unwrap();
+        fs::write(legacy_dir.join("cultura.db"), "db").unwrap();
+        fs::write(legacy_dir.join("cultura.log"), "legacy log").unwrap();
+
+        let l = Locations::new(home.path());
+        fs::create_dir_all(&l.state_dir).unwrap();
+        fs::write(l.state_dir.join("cultura.log"), "log").unwrap();
+        l.migrate(&[
+            ("config.toml", &l.config_dir),
+            ("cultura.db", &l.data_dir),
+            ("cultura.log", &l.state_dir),
+            ("cultura.pid", &l.runtime_dir),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(l.config_dir.join("config.toml")).unwrap(),
+            "config"
+        );
+        assert_eq!(
+            fs::read_to_string(l.data_dir.join("cultura.db")).unwrap(),
+            "db"
+        );
+        assert!(!legacy_dir.join("cultura.db").exists());
+        assert_eq!(
+            fs::read_to_string(l.state_dir.join("cultura.log")).unwrap(),
+            "log"
+        );
+        assert!(legacy_dir.join("cultura.log").exists());
+    }
+
+    #[test]
+    fn test_migrate_to_cultura_home() {
+        let home = tempdir().unwrap();
+        let cultura_home = tempdir().unwrap();
+        let legacy_dir = home.path().join(".config/cultura");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("config.toml"), "config").unwrap();
+
+        let l = resolve(Some(home.path().to_path_buf()), |name| match name {
+            "CULTURA_HOME" => Some(cultura_home.path().display().to_string()),
+            _ => None,
+        })
+        .unwrap();
+        l.migrate(&[("config.toml", &l.config_dir)]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(cultura_home.path().join("config.toml")).unwrap(),
+            "config"
+        );
+        assert!(!legacy_dir.exists());
+    }
+
+    #[test]
+    fn test_remove_files() {
+        let home = tempdir().unwrap();
+        let l = Locations::new(home.path());
+        l.create_dirs().unwrap();
+        fs::write(l.config_dir.join("config.toml"), "config").unwrap();
+        fs::write(l.config_dir.join("notes.txt"), "notes").unwrap();
+        fs::write(l.data_dir.join("cultura.db"), "db").unwrap();
+
+        l.remove_files(&[
+            ("config.toml", &l.config_dir),
+            ("cultura.db", &l.data_dir),
+            ("cultura.log", &l.state_dir),
+        ])
+        .unwrap();
+
+        assert!(!l.config_dir.join("config.toml").exists());
+        assert_eq!(
+            fs::read_to_string(l.config_dir.join("notes.txt")).unwrap(),
+            "notes"
+        );
+        assert!(!l.data_dir.exists());
+        assert!(!l.state_dir.exists());
+    }
+}
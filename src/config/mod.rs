@@ -1,87 +1,514 @@
+mod locations;
+mod profile;
+pub mod schema;
+mod upgrade;
+mod validation;
+
 use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
+    collections::BTreeMap,
     error::Error,
     fmt::{self, Display},
-    fs::{self, DirBuilder},
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
 };
 
-use crate::third_part::{self, Crawler};
+pub use locations::Locations;
+use profile::Profile;
+use toml::{value::Table, Value};
+pub use upgrade::CONFIG_VERSION;
+pub use validation::Issue;
+
+use crate::{
+    daemon::Scheduler,
+    fact::{
+        self,
+        duplicate::Deduplication,
+        filter::Rules,
+        harvest::Harvest,
+        health::Health,
+        layout::Layout,
+        normaliser::{self, Step},
+        theme::Theme,
+        SelectionStrategy,
+    },
+    third_part::{self, http::Network, Crawler},
+};
 const CONFIG_FILE_NAME: &str = "config.toml";
 const DATABASE_NAME: &str = "cultura.db";
+const LOG_FILE_NAME: &str = "cultura.log";
+const PID_FILE_NAME: &str = "cultura.pid";
+// The copy of the config file given to the editor
+const EDITED_CONFIG_FILE_NAME: &str = "config.edit.toml";
 const DEFAULT_TEMPLATE: &str = r#"
 __|>__:cyan $fact:yellow
 "#;
 
 #[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    #[serde(default = "get_config_version")]
+    version: usize,
     providers: Vec<Box<dyn Crawler>>,
+    // The providers kept with their settings but not harvested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    disabled_providers: Vec<String>,
     template: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    #[serde(default, skip_serializing_if = "SelectionStrategy::is_default")]
+    selection_strategy: SelectionStrategy,
+    #[serde(default, skip_serializing_if = "Theme::is_default")]
+    theme: Theme,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    normalisation: BTreeMap<String, Vec<Step>>,
+    #[serde(default, skip_serializing_if = "Rules::is_empty")]
+    filters: Rules,
+    #[serde(default, skip_serializing_if = "Deduplication::is_default")]
+    deduplication: Deduplication,
+    #[serde(default, skip_serializing_if = "Network::is_default")]
+    network: Network,
+    #[serde(default, skip_serializing_if = "Harvest::is_default")]
+    harvest: Harvest,
+    #[serde(default, skip_serializing_if = "Health::is_default")]
+    health: Health,
+    #[serde(default, skip_serializing_if = "Scheduler::is_default")]
+    scheduler: Scheduler,
+    #[serde(default, skip_serializing_if = "Layout::is_default")]
+    layout: Layout,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    fn get_provider_state(&self, id: &str) -> ProviderState {
+        if !self.providers.iter().any(|p| p.get_id() == id) {
+            ProviderState::NotAdded
+        } else if self.disabled_providers.iter().any(|p| p == id) {
+            ProviderState::Disabled
+        } else {
+            ProviderState::Enabled
+        }
+    }
 }
 
 impl Display for Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            r#"providers => {:?}
-template  => {}"#,
+            r#"providers          => {:?}
+disabled_providers => {:?}
+template           => {}
+profile            => {}
+selection_strategy => {}
+theme              => {}
+normalisation      => {:?}
+filters            => {}
+deduplication      => {}
+network            => {}
+harvest            => {}
+health             => {}
+scheduler          => {}
+layout             => {}
+profiles           => {:?}"#,
             self.providers
                 .iter()
                 .map(|p| p.get_id())
                 .collect::<Vec<String>>(),
+            self.disabled_providers,
             self.template,
+            self.profile.clone().unwrap_or("none".to_string()),
+            self.selection_strategy,
+            self.theme,
+            self.normalisation
+                .iter()
+                .map(|(provider, steps)| format!(
+                    "{}: {}",
+                    provider,
+                    steps
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))
+                .collect::<Vec<String>>(),
+            self.filters,
+            self.deduplication,
+            self.network,
+            self.harvest,
+            self.health,
+            self.scheduler,
+            self.layout,
+            self.profiles.keys().collect::<Vec<&String>>(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProviderState {
+    Enabled,
+    Disabled,
+    NotAdded,
+}
+
+impl Display for ProviderState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProviderState::Enabled => "enabled",
+            ProviderState::Disabled => "disabled",
+            ProviderState::NotAdded => "not added",
+        };
+        f.pad(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+    Env,
+    Cli(String),
+}
+
+// Values overriding the ones of the config file, they are never saved
+#[derive(Clone)]
+pub struct Layer {
+    origin: Origin,
+    value: Value,
+}
+
+// The overrides by order of precedence: the CULTURA_* environment variables
+// then the file given with --config.
+pub fn load_overrides(config_file: Option<&str>) -> Result<Vec<Layer>, Box<dyn Error>> {
+    let mut layers = vec![get_env_layer(|name| std::env::var(name).ok())?];
+    if let Some(path) = config_file {
+        let s = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        layers.push(Layer {
+            origin: Origin::Cli(path.to_string()),
+            value: toml::from_str(&s).map_err(|e| format!("cannot parse {}: {}", path, e))?,
+        });
+    }
+    Ok(layers)
+}
+
+fn get_env_layer(var: impl Fn(&str) -> Option<String>) -> Result<Layer, Box<dyn Error>> {
+    let mut value = Value::Table(Table::new());
+    for key in schema::KEYS {
+        let name = schema::get_env_var(key);
+        if let Some(raw) = var(&name) {
+            let v = schema::parse_value(key, &raw)
+                .map_err(|e| format!("invalid value for {}: {}", name, e))?;
+            schema::set(&mut value, key.path, v);
+        }
+    }
+    Ok(Layer {
+        origin: Origin::Env,
+        value,
+    })
+}
+
+// Open a copy of the config file in an editor, the copy replaces the config
+// file only once it is valid. When it is not, retry is given the issues and
+// tells whether the copy must be edited again. Return whether the config file
+// has been replaced.
+pub fn edit(
+    locations: &Locations,
+    mut edit: impl FnMut(&Path) -> Result<(), Box<dyn Error>>,
+    mut retry: impl FnMut(&[Issue]) -> bool,
+) -> Result<bool, Box<dyn Error>> {
+    prepare(locations)?;
+    let config_file_path = locations.config_dir.join(CONFIG_FILE_NAME);
+    let edited_file_path = locations.config_dir.join(EDITED_CONFIG_FILE_NAME);
+    match fs::read_to_string(&config_file_path) {
+        Ok(s) => fs::write(&edited_file_path, s)?,
+        Err(_) => fs::write(&edited_file_path, toml::to_string(&get_default_config())?)?,
+    }
+
+    let result: Result<bool, Box<dyn Error>> = loop {
+        if let Err(e) = edit(&edited_file_path) {
+            break Err(e);
+        }
+        let s = match fs::read_to_string(&edited_file_path) {
+            Ok(s) => s,
+            Err(e) => break Err(e.into()),
+        };
+        match validation::validate(&s) {
+            Ok(_) => {
+                break fs::rename(&edited_file_path, &config_file_path)
+                    .map(|_| true)
+                    .map_err(|e| e.into())
+            }
+            Err(issues) if retry(&issues) => continue,
+            Err(_) => break Ok(false),
+        }
+    };
+    let _ = fs::remove_file(&edited_file_path);
+    result
+}
+
+fn prepare(locations: &Locations) -> Result<(), Box<dyn Error>> {
+    locations.migrate(&[
+        (CONFIG_FILE_NAME, &locations.config_dir),
+        (DATABASE_NAME, &locations.data_dir),
+        (LOG_FILE_NAME, &locations.state_dir),
+        (PID_FILE_NAME, &locations.runtime_dir),
+    ])?;
+    locations.create_dirs()
+}
+
+fn parse_config(config_file_path: &str, content: &str) -> Result<Config, Box<dyn Error>> {
+    let mut config = validation::validate(content).map_err(|issues| {
+        format!(
+            "invalid config file {}:\n{}",
+            config_file_path,
+            issues
+                .iter()
+                .map(|i| format!("  {}", i))
+                .collect::<Vec<String>>()
+                .join("\n")
         )
+    })?;
+    if config.providers.is_empty() {
+        config.providers = get_all_providers();
+    }
+    Ok(config)
+}
+
+// Load a config file upgraded with the given upgrades, the upgraded config is
+// validated before the previous file is copied to a .bak file and replaced.
+fn load_config_file(
+    config_file_path: &str,
+    upgrades: &[upgrade::Upgrade],
+) -> Result<Config, Box<dyn Error>> {
+    let s = fs::read_to_string(config_file_path)?;
+    match upgrade::upgrade(&s, upgrades)? {
+        Some(upgraded) => {
+            let config = parse_config(config_file_path, &upgraded)?;
+            fs::copy(config_file_path, format!("{}.bak", config_file_path))?;
+            fs::write(config_file_path, &upgraded)?;
+            Ok(config)
+        }
+        None => parse_config(config_file_path, &s),
+    }
+}
+
+fn get_all_providers() -> Vec<Box<dyn Crawler>> {
+    let mut providers = third_part::get_available_providers()
+        .into_values()
+        .collect::<Vec<Box<dyn Crawler>>>();
+    providers.sort_by_key(|p| p.get_id());
+    providers
+}
+
+// A file without version is a version 1 file, the ones of the previous
+// versions are upgraded before being loaded
+fn get_config_version() -> usize {
+    CONFIG_VERSION
+}
+
+fn get_default_config() -> Config {
+    Config {
+        version: CONFIG_VERSION,
+        template: String::from(DEFAULT_TEMPLATE),
+        ..Config::default()
     }
 }
 
 #[derive(Clone, Default)]
 pub struct ConfigResolver {
-    home_dir: String,
+    locations: Locations,
+    // The config as stored in the config file
+    file_config: RefCell<Config>,
+    overrides: Vec<Layer>,
+    // The config file with the overrides applied
     config: RefCell<Config>,
 }
 
 impl ConfigResolver {
-    pub fn new(path: Option<PathBuf>) -> Result<ConfigResolver, Box<dyn Error>> {
-        match path {
-            Some(path) => {
-                let mut c = ConfigResolver {
-                    home_dir: path.display().to_string(),
-                    ..ConfigResolver::default()
-                };
-                let config_file_path = c.resolve_relative_path(CONFIG_FILE_NAME);
-
-                DirBuilder::new()
-                    .recursive(true)
-                    .create(c.get_root_config_path())?;
-
-                if std::path::Path::new(&config_file_path).exists() {
-                    let s = fs::read_to_string(c.resolve_relative_path(CONFIG_FILE_NAME))?;
-                    c.config = RefCell::new(toml::from_str(s.as_str())?);
-                    if c.config.borrow().providers.is_empty() {
-                        c.config.borrow_mut().providers = third_part::get_available_providers()
-                            .values()
-                            .cloned()
-                            .collect();
-                    }
-                } else {
-                    let mut config = Config::default();
-                    config.template = String::from(DEFAULT_TEMPLATE);
-                    save_config(config.clone(), &c)?;
-                    c.config = RefCell::new(config);
-                }
-                Ok(c)
+    pub fn new(locations: Locations) -> Result<ConfigResolver, Box<dyn Error>> {
+        prepare(&locations)?;
+
+        let c = ConfigResolver {
+            locations,
+            ..ConfigResolver::default()
+        };
+        let config_file_path = c.get_config_file_path();
+
+        let config = if std::path::Path::new(&config_file_path).exists() {
+            load_config_file(&config_file_path, upgrade::UPGRADES)?
+        } else {
+            // The providers are written right away so the first fact, from the
+            // starter pack, is picked among them
+            let config = Config {
+                providers: get_all_providers(),
+                ..get_default_config()
+            };
+            save_config(config.clone(), &c)?;
+            config
+        };
+        c.file_config.replace(config);
+        c.refresh()?;
+        Ok(c)
+    }
+
+    // Load the config without writing anything, neither the directories nor
+    // the config file, the default config is used when the file is missing.
+    pub fn open(locations: Locations) -> Result<ConfigResolver, Box<dyn Error>> {
+        let c = ConfigResolver {
+            locations,
+            ..ConfigResolver::default()
+        };
+        let config_file_path = c.get_config_file_path();
+        let config = match fs::read_to_string(&config_file_path) {
+            Ok(s) => {
+                let s = upgrade::upgrade(&s, upgrade::UPGRADES)?.unwrap_or(s);
+                parse_config(&config_file_path, &s)?
             }
-            None => Err("path cannot be found".to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config {
+                providers: get_all_providers(),
+                ..get_default_config()
+            },
+            Err(e) => Err(e)?,
+        };
+        c.file_config.replace(config);
+        c.refresh()?;
+        Ok(c)
+    }
+
+    pub fn with_overrides(mut self, overrides: Vec<Layer>) -> Result<Self, Box<dyn Error>> {
+        self.overrides = overrides;
+        self.refresh()?;
+        Ok(self)
+    }
+
+    // The settings are applied in this order: the config file, the active
+    // profile then the overrides.
+    fn refresh(&self) -> Result<(), Box<dyn Error>> {
+        let mut value = Value::try_from(self.file_config.borrow().clone())?;
+        let mut overrides = Value::Table(Table::new());
+        for layer in &self.overrides {
+            schema::merge(&mut overrides, layer.value.clone());
         }
+
+        // The profile can be selected by an override, CULTURA_PROFILE for instance
+        let mut config_value = value.clone();
+        schema::merge(&mut config_value, overrides.clone());
+        let config: Config = config_value.try_into()?;
+        let config = match &config.profile {
+            Some(name) => {
+                let profile = match config.profiles.get(name) {
+                    Some(profile) => profile,
+                    None => Err(format!("unknown profile {}", name))?,
+                };
+                schema::merge(&mut value, Value::try_from(profile)?);
+                schema::merge(&mut value, overrides);
+                value.try_into()?
+            }
+            None => config,
+        };
+        self.config.replace(config);
+        Ok(())
     }
 
-    fn get_root_config_path(&self) -> String {
-        format!("{}/.config/cultura", self.home_dir)
+    fn update(&self, f: impl FnOnce(&mut Config)) -> Result<(), Box<dyn Error>> {
+        f(&mut self.file_config.borrow_mut());
+        save_config(self.file_config.borrow().clone(), self)?;
+        self.refresh()
     }
 
-    pub fn resolve_relative_path(&self, path: &str) -> String {
-        format!("{}/{}", self.get_root_config_path(), path)
+    // Apply a change to the config file as a TOML value, the keys removed fall
+    // back to their default value and the config is saved only if valid.
+    fn update_value(&self, f: impl FnOnce(&mut Value)) -> Result<(), Box<dyn Error>> {
+        let mut file_value = Value::try_from(self.file_config.borrow().clone())?;
+        f(&mut file_value);
+        let mut value = get_default_value()?;
+        schema::merge(&mut value, file_value);
+        let config = validation::validate(&toml::to_string(&value)?).map_err(|issues| {
+            issues
+                .iter()
+                .map(|i| i.message.clone())
+                .collect::<Vec<String>>()
+                .join(", ")
+        })?;
+        self.update(|c| *c = config)
+    }
+
+    // The value of a key given by its dotted path, the overrides included
+    pub fn get_value(&self, path: &str) -> Result<Option<Value>, Box<dyn Error>> {
+        schema::find_key(path)?;
+        let value = Value::try_from(self.get_config())?;
+        Ok(schema::get(&value, path)
+            .or(schema::get(&get_default_value()?, path))
+            .cloned())
+    }
+
+    pub fn set_value(&self, path: &str, raw: &str) -> Result<(), Box<dyn Error>> {
+        let new_value = schema::parse_value(schema::find_key(path)?, raw)?;
+        self.update_value(|value| schema::set(value, path, new_value))
+    }
+
+    pub fn unset_value(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        schema::find_key(path)?;
+        self.update_value(|value| {
+            schema::remove(value, path);
+        })
+    }
+
+    // Every key with its value and where the value comes from
+    pub fn dump_with_origin(&self) -> Result<String, Box<dyn Error>> {
+        let config_file_path = self.get_config_file_path();
+        let file_value: Value = match fs::read_to_string(&config_file_path) {
+            Ok(s) => toml::from_str(&s)?,
+            Err(_) => Value::Table(Table::new()),
+        };
+        let config = self.get_config();
+        let value = Value::try_from(&config)?;
+        let default_value = get_default_value()?;
+        let profile_value = match config.profile.as_ref().and_then(|p| config.profiles.get(p)) {
+            Some(profile) => Value::try_from(profile)?,
+            None => Value::Table(Table::new()),
+        };
+
+        Ok(schema::KEYS
+            .iter()
+            .map(|key| {
+                let origin = match self
+                    .overrides
+                    .iter()
+                    .rev()
+                    .find(|l| schema::get(&l.value, key.path).is_some())
+                {
+                    Some(Layer {
+                        origin: Origin::Env,
+                        ..
+                    }) => format!("env {}", schema::get_env_var(key)),
+                    Some(Layer {
+                        origin: Origin::Cli(path),
+                        ..
+                    }) => format!("--config {}", path),
+                    None if schema::get(&profile_value, key.path).is_some() => {
+                        format!("profile {}", config.profile.clone().unwrap_or_default())
+                    }
+                    None if schema::get(&file_value, key.path).is_some() => {
+                        format!("file {}", config_file_path)
+                    }
+                    None => "default".to_string(),
+                };
+                format!(
+                    "{} = {} # {}",
+                    key.path,
+                    schema::get(&value, key.path)
+                        .or(schema::get(&default_value, key.path))
+                        .map(|v| match v {
+                            // Keep the value on one line
+                            Value::String(s) => format!("{:?}", s),
+                            v => v.to_string(),
+                        })
+                        .unwrap_or("unset".to_string()),
+                    origin
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"))
     }
 
     pub fn get_config(&self) -> Config {
@@ -89,13 +516,45 @@ impl ConfigResolver {
     }
 
     pub fn get_config_file_path(&self) -> String {
-        self.resolve_relative_path(CONFIG_FILE_NAME)
+        to_string(self.locations.config_dir.join(CONFIG_FILE_NAME))
     }
 
     pub fn set_template(&self, template: String) -> Result<(), Box<dyn Error>> {
-        self.config.borrow_mut().template = template;
-        save_config(self.config.borrow().clone(), self)?;
-        Ok(())
+        fact::check_template(&template)?;
+        let profile = self.get_active_profile();
+        self.update(
+            |c| match profile.and_then(|name| c.profiles.get_mut(&name)) {
+                Some(profile) => profile.template = Some(template),
+                None => c.template = template,
+            },
+        )
+    }
+
+    // The settings a profile can define are written to the active profile,
+    // they would be overridden by the profile otherwise.
+    pub fn get_active_profile(&self) -> Option<String> {
+        self.config.borrow().profile.clone()
+    }
+
+    pub fn use_profile(&self, name: Option<String>) -> Result<(), Box<dyn Error>> {
+        if let Some(name) = &name {
+            if !self.config.borrow().profiles.contains_key(name) {
+                Err(format!("unknown profile {}", name))?
+            }
+        }
+        self.update(|c| c.profile = name)
+    }
+
+    pub fn get_selection_strategy(&self) -> SelectionStrategy {
+        self.config.borrow().selection_strategy
+    }
+
+    pub fn get_theme(&self) -> Theme {
+        self.config.borrow().theme
+    }
+
+    pub fn set_theme(&self, theme: Theme) -> Result<(), Box<dyn Error>> {
+        self.update(|c| c.theme = theme)
     }
 
     pub fn get_template(&self) -> String {
@@ -104,6 +563,13 @@ impl ConfigResolver {
 
     pub fn set_providers(&self, providers: Vec<String>) -> Result<(), Box<dyn Error>> {
         let available_providers = third_part::get_available_providers();
+        // TIL,DYK is accepted as well as TIL DYK
+        let providers = providers
+            .iter()
+            .flat_map(|p| p.split(','))
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<String>>();
         let ps = providers
             .clone()
             .into_iter()
@@ -113,23 +579,177 @@ impl ConfigResolver {
 
         if ps.len() != providers.len() {
             Err("some providers are invalid")?
+        } else if let Some(id) = providers
+            .iter()
+            .enumerate()
+            .find(|(i, p)| providers[..*i].contains(p))
+            .map(|(_, p)| p)
+        {
+            Err(format!("the provider {} is given twice", id))?
         } else {
-            self.config.borrow_mut().providers = ps;
-            save_config(self.config.borrow().clone(), self)?;
-            Ok(())
+            let profile = self.get_active_profile();
+            self.update(
+                |c| match profile.and_then(|name| c.profiles.get_mut(&name)) {
+                    Some(profile) => profile.providers = Some(ps),
+                    None => c.providers = ps,
+                },
+            )
         }
     }
 
+    // The providers to harvest, the disabled ones are left out
     pub fn get_providers(&self) -> Vec<Box<dyn Crawler>> {
-        self.config.borrow().providers.clone()
+        let config = self.config.borrow();
+        config
+            .providers
+            .iter()
+            .filter(|p| !config.disabled_providers.contains(&p.get_id()))
+            .cloned()
+            .collect()
+    }
+
+    // Every available provider with its state, sorted by id
+    pub fn list_providers(&self) -> Vec<(Box<dyn Crawler>, ProviderState)> {
+        let config = self.config.borrow();
+        let mut providers = third_part::get_available_providers()
+            .into_values()
+            .map(|provider| {
+                let state = config.get_provider_state(&provider.get_id());
+                // The provider is listed with its own settings
+                match config
+                    .providers
+                    .iter()
+                    .find(|p| p.get_id() == provider.get_id())
+                {
+                    Some(p) => (p.clone(), state),
+                    None => (provider, state),
+                }
+            })
+            .collect::<Vec<(Box<dyn Crawler>, ProviderState)>>();
+        providers.sort_by_key(|(p, _)| p.get_id());
+        providers
+    }
+
+    fn get_provider_state(&self, id: &str) -> Result<ProviderState, Box<dyn Error>> {
+        if !third_part::get_available_providers().contains_key(id) {
+            Err(format!("unknown provider {}", id))?
+        }
+        Ok(self.config.borrow().get_provider_state(id))
+    }
+
+    // The providers are edited in the active profile when it defines them,
+    // they would be overridden by the profile otherwise.
+    fn update_providers(
+        &self,
+        f: impl FnOnce(&mut Vec<Box<dyn Crawler>>) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let profile = self.get_active_profile();
+        let mut config = self.file_config.borrow().clone();
+        match profile
+            .and_then(|name| config.profiles.get_mut(&name))
+            .and_then(|p| p.providers.as_mut())
+        {
+            Some(providers) => f(providers)?,
+            None => f(&mut config.providers)?,
+        }
+        // A provider removed from every list is not disabled anymore
+        let added = config
+            .profiles
+            .values()
+            .filter_map(|p| p.providers.as_ref())
+            .chain([&config.providers])
+            .flatten()
+            .map(|p| p.get_id())
+            .collect::<Vec<String>>();
+        config.disabled_providers.retain(|id| added.contains(id));
+        self.update(|c| *c = config)
+    }
+
+    pub fn add_provider(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        let provider = third_part::get_available_providers()
+            .remove(id)
+            .ok_or(format!("unknown provider {}", id))?;
+        self.update_providers(|providers| {
+            if providers.iter().any(|p| p.get_id() == id) {
+                Err(format!("the provider {} is already added", id))?
+            }
+            providers.push(provider);
+            Ok(())
+        })
+    }
+
+    pub fn remove_provider(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        if !third_part::get_available_providers().contains_key(id) {
+            Err(format!("unknown provider {}", id))?
+        }
+        self.update_providers(|providers| {
+            if !providers.iter().any(|p| p.get_id() == id) {
+                Err(format!("the provider {} is not added", id))?
+            }
+            // An empty list of providers stands for all of them
+            if providers.len() == 1 {
+                Err("the last provider cannot be removed, disable it instead")?
+            }
+            providers.retain(|p| p.get_id() != id);
+            Ok(())
+        })
+    }
+
+    pub fn enable_provider(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        match self.get_provider_state(id)? {
+            ProviderState::NotAdded => Err(format!("the provider {} is not added", id))?,
+            ProviderState::Enabled => Err(format!("the provider {} is already enabled", id))?,
+            ProviderState::Disabled => self.update(|c| c.disabled_providers.retain(|p| p != id)),
+        }
+    }
+
+    pub fn disable_provider(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        match self.get_provider_state(id)? {
+            ProviderState::NotAdded => Err(format!("the provider {} is not added", id))?,
+            ProviderState::Disabled => Err(format!("the provider {} is already disabled", id))?,
+            ProviderState::Enabled => self.update(|c| c.disabled_providers.push(id.to_string())),
+        }
+    }
+
+    pub fn get_normalisation_steps(&self, provider: &str) -> Vec<Step> {
+        self.config
+            .borrow()
+            .normalisation
+            .get(provider)
+            .cloned()
+            .unwrap_or_else(normaliser::get_default_steps)
+    }
+
+    pub fn get_filter_rules(&self) -> Rules {
+        self.config.borrow().filters.clone()
+    }
+
+    pub fn get_deduplication(&self) -> Deduplication {
+        self.config.borrow().deduplication.clone()
+    }
+
+    pub fn get_network(&self) -> Network {
+        self.config.borrow().network.clone()
+    }
+
+    pub fn get_harvest(&self) -> Harvest {
+        self.config.borrow().harvest.clone()
+    }
+
+    pub fn get_health(&self) -> Health {
+        self.config.borrow().health.clone()
     }
 
     pub fn get_database_path(&self) -> String {
-        self.resolve_relative_path(DATABASE_NAME)
+        to_string(self.locations.data_dir.join(DATABASE_NAME))
     }
 
     pub fn get_pid_file(&self) -> String {
-        self.resolve_relative_path("cultura.pid")
+        to_string(self.locations.runtime_dir.join(PID_FILE_NAME))
+    }
+
+    pub fn get_log_file(&self) -> String {
+        to_string(self.locations.state_dir.join(LOG_FILE_NAME))
     }
 
     pub fn get_daemon_pid(&self) -> Result<i32, Box<dyn Error>> {
@@ -138,29 +758,66 @@ impl ConfigResolver {
         Ok(pid)
     }
 
-    pub fn get_working_dir(&self) -> &str {
-        "/tmp"
+    pub fn get_working_dir(&self) -> String {
+        to_string(self.locations.runtime_dir.clone())
+    }
+
+    pub fn get_scheduler(&self) -> Scheduler {
+        self.config.borrow().scheduler.clone()
     }
 
-    pub fn get_scheduler_interval_as_minutes(&self) -> u64 {
-        5
+    pub fn get_layout(&self) -> Layout {
+        self.config.borrow().layout.clone()
     }
 
+    // Remove the files of cultura, the other files of its directories are kept
     pub fn clear_all(&self) -> Result<(), Box<dyn Error>> {
-        fs::remove_dir_all(self.get_root_config_path())?;
-        Ok(())
+        let l = &self.locations;
+        self.locations.remove_files(&[
+            (CONFIG_FILE_NAME, &l.config_dir),
+            (&format!("{}.bak", CONFIG_FILE_NAME), &l.config_dir),
+            (EDITED_CONFIG_FILE_NAME, &l.config_dir),
+            (DATABASE_NAME, &l.data_dir),
+            (LOG_FILE_NAME, &l.state_dir),
+            (&format!("{}.1", LOG_FILE_NAME), &l.state_dir),
+            (PID_FILE_NAME, &l.runtime_dir),
+        ])
     }
 }
 
 fn save_config(config: Config, config_resolver: &ConfigResolver) -> Result<(), Box<dyn Error>> {
     let toml = toml::to_string(&config).unwrap();
-    fs::write(
-        config_resolver.resolve_relative_path(CONFIG_FILE_NAME),
-        toml,
-    )?;
+    fs::write(config_resolver.get_config_file_path(), toml)?;
     Ok(())
 }
 
+// The sections are not serialized when they hold their default value
+fn get_default_value() -> Result<Value, Box<dyn Error>> {
+    let mut value = Value::try_from(get_default_config())?;
+    schema::set(&mut value, "disabled_providers", Value::Array(vec![]));
+    schema::set(&mut value, "normalisation", Value::Table(Table::new()));
+    schema::set(&mut value, "filters", Value::try_from(Rules::default())?);
+    schema::set(
+        &mut value,
+        "deduplication",
+        Value::try_from(Deduplication::default())?,
+    );
+    schema::set(&mut value, "network", Value::try_from(Network::default())?);
+    schema::set(&mut value, "harvest", Value::try_from(Harvest::default())?);
+    schema::set(&mut value, "health", Value::try_from(Health::default())?);
+    schema::set(
+        &mut value,
+        "scheduler",
+        Value::try_from(Scheduler::default())?,
+    );
+    schema::set(&mut value, "layout", Value::try_from(Layout::default())?);
+    Ok(value)
+}
+
+fn to_string(path: PathBuf) -> String {
+    path.display().to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use regex::Regex;
@@ -171,29 +828,36 @@ mod tests {
     #[test]
     fn test_config_resolver() {
         {
-            let c = ConfigResolver::new(Some(tempdir().unwrap().into_path()));
+            let c = ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path()));
             match c {
                 Ok(config) => {
                     assert!(
-                        Regex::new(r"^.*?/.config/cultura$")
+                        Regex::new(r"^.*?/.config/cultura/config.toml$")
                             .unwrap()
-                            .is_match(&config.get_root_config_path()),
-                        "root_config_path = {}",
-                        &config.get_root_config_path(),
+                            .is_match(&config.get_config_file_path()),
+                        "config_file_path = {}",
+                        &config.get_config_file_path(),
                     );
                     assert!(
-                        Regex::new(r"^.*?/.config/cultura/cultura.db$")
+                        Regex::new(r"^.*?/.local/share/cultura/cultura.db$")
                             .unwrap()
                             .is_match(&config.get_database_path()),
                         "database_path = {}",
                         &config.get_database_path(),
                     );
                     assert!(
-                        Regex::new(r"^.*?/.config/cultura/cultura.pid$")
+                        Regex::new(r"^.*?/.local/state/cultura/cultura.pid$")
                             .unwrap()
                             .is_match(&config.get_pid_file()),
                         "pid_file = {}",
-                        &config.get_database_path(),
+                        &config.get_pid_file(),
+                    );
+                    assert!(
+                        Regex::new(r"^.*?/.local/state/cultura/cultura.log$")
+                            .unwrap()
+                            .is_match(&config.get_log_file()),
+                        "log_file = {}",
+                        &config.get_log_file(),
                     );
                 }
                 Err(e) => panic!("{}", e),
@@ -203,21 +867,507 @@ mod tests {
 
     #[test]
     fn test_accessors_providers() {
-        let c = ConfigResolver::new(Some(tempdir().unwrap().into_path())).unwrap();
+        let c = ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
         match c.set_providers(vec!["whatever".to_string()]) {
             Err(e) => assert_eq!(e.to_string(), "some providers are invalid"),
             Ok(_) => panic!("must return an error"),
         };
 
-        match c.set_providers(vec!["TIL".to_string()]) {
-            Err(e) => panic!("must return no error: {}", e),
-            Ok(_) => (),
+        if let Err(e) = c.set_providers(vec!["TIL".to_string()]) {
+            panic!("must return no error: {}", e)
         };
         println!("{}", c.get_config());
         assert_eq!(c.get_providers().len(), 1);
         assert_eq!(c.get_providers().first().unwrap().get_id(), "TIL");
 
-        let c2 = ConfigResolver::new(Some(tempdir().unwrap().into_path())).unwrap();
-        assert_eq!(c2.get_providers().len(), 0);
+        let c2 = ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
+        assert_eq!(
+            c2.get_providers()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>(),
+            vec!["DYK", "STARTER", "TIL"]
+        );
+    }
+
+    #[test]
+    fn test_accessors_normalisation_steps() {
+        let path = tempdir().unwrap().into_path();
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        assert_eq!(
+            c.get_normalisation_steps("TIL"),
+            normaliser::get_default_steps()
+        );
+
+        fs::write(
+            c.get_config_file_path(),
+            r#"template = "$fact"
+
+[[providers]]
+provider = "TIL"
+
+[[normalisation.TIL]]
+step = "regex_replace"
+pattern = "^TIL"
+replacement = "Today I learned"
+
+[[normalisation.TIL]]
+step = "capitalise"
+"#,
+        )
+        .unwrap();
+
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        assert_eq!(
+            c.get_normalisation_steps("TIL"),
+            vec![
+                Step::RegexReplace {
+                    pattern: "^TIL".to_string(),
+                    replacement: "Today I learned".to_string()
+                },
+                Step::Capitalise
+            ]
+        );
+        assert_eq!(
+            c.get_normalisation_steps("DYK"),
+            normaliser::get_default_steps()
+        );
+    }
+
+    #[test]
+    fn test_overrides() {
+        let path = tempdir().unwrap().into_path();
+        let cli_file = path.join("cli.toml");
+        fs::write(&cli_file, "[harvest]\nconcurrency = 16\n").unwrap();
+        let mut overrides = vec![get_env_layer(|name| match name {
+            "CULTURA_TEMPLATE" => Some("$fact:red".to_string()),
+            "CULTURA_HARVEST_CONCURRENCY" => Some("8".to_string()),
+            "CULTURA_HARVEST_DEADLINE" => Some("1m".to_string()),
+            _ => None,
+        })
+        .unwrap()];
+        overrides.append(&mut load_overrides(cli_file.to_str()).unwrap()[1..].to_vec());
+
+        let c = ConfigResolver::new(Locations::new(&path))
+            .unwrap()
+            .with_overrides(overrides)
+            .unwrap();
+        assert_eq!(c.get_template(), "$fact:red");
+        assert_eq!(c.get_harvest().concurrency, 16);
+        assert_eq!(c.get_harvest().deadline, std::time::Duration::from_secs(60));
+
+        // The overrides are never saved
+        c.set_providers(vec!["TIL".to_string()]).unwrap();
+        assert_eq!(c.get_template(), "$fact:red");
+        let saved = fs::read_to_string(c.get_config_file_path()).unwrap();
+        assert!(saved.contains(r#"provider = "TIL""#), "{}", saved);
+        assert!(!saved.contains("$fact:red"), "{}", saved);
+        assert!(!saved.contains("harvest"), "{}", saved);
+
+        let dump = c.dump_with_origin().unwrap();
+        let lines = dump.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            lines[0],
+            format!(
+                r#"providers = [{{ provider = "TIL" }}] # file {}"#,
+                c.get_config_file_path()
+            )
+        );
+        assert_eq!(lines[1], r#"template = "$fact:red" # env CULTURA_TEMPLATE"#);
+        assert!(lines.contains(
+            &format!("harvest.concurrency = 16 # --config {}", cli_file.display()).as_str()
+        ));
+        assert!(lines.contains(&r#"harvest.deadline = "1m" # env CULTURA_HARVEST_DEADLINE"#));
+        assert!(lines.contains(&"deduplication.threshold = 0.6 # default"));
+        assert!(lines.contains(&"network.proxy = unset # default"));
+    }
+
+    #[test]
+    fn test_overrides_with_invalid_values() {
+        match get_env_layer(|name| match name {
+            "CULTURA_DEDUPLICATION_ENABLED" => Some("yes".to_string()),
+            _ => None,
+        }) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "invalid value for CULTURA_DEDUPLICATION_ENABLED: provided string was not `true` or `false`"
+            ),
+        }
+        match load_overrides(Some("/a/missing/config.toml")) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("cannot read /a/missing/config.toml")),
+        }
+    }
+
+    #[test]
+    fn test_profiles() {
+        let path = tempdir().unwrap().into_path();
+        let locations = Locations::new(&path);
+        fs::create_dir_all(&locations.config_dir).unwrap();
+        fs::write(
+            locations.config_dir.join(CONFIG_FILE_NAME),
+            r#"template = "$fact"
+
+[[providers]]
+provider = "TIL"
+
+[[providers]]
+provider = "DYK"
+
+[filters]
+blocklist = ["war"]
+
+[profiles.work]
+template = "$fact:blue"
+selection_strategy = "random"
+
+[[profiles.work.providers]]
+provider = "DYK"
+
+[profiles.work.filters]
+skip_nsfw = ["DYK"]
+"#,
+        )
+        .unwrap();
+
+        let c = ConfigResolver::new(locations.clone()).unwrap();
+        assert_eq!(c.get_template(), "$fact");
+        assert_eq!(c.get_providers().len(), 2);
+        assert_eq!(c.get_selection_strategy(), SelectionStrategy::Newest);
+
+        c.use_profile(Some("work".to_string())).unwrap();
+        assert_eq!(c.get_template(), "$fact:blue");
+        assert_eq!(
+            c.get_providers()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>(),
+            vec!["DYK"]
+        );
+        assert_eq!(c.get_selection_strategy(), SelectionStrategy::Random);
+        assert_eq!(
+            c.get_filter_rules(),
+            Rules {
+                blocklist: vec!["war".to_string()],
+                skip_nsfw: vec!["DYK".to_string()],
+                ..Rules::default()
+            }
+        );
+        let dump = c.dump_with_origin().unwrap();
+        assert!(dump.contains(r#"template = "$fact:blue" # profile work"#));
+        assert!(dump.contains(r#"filters.skip_nsfw = ["DYK"] # profile work"#));
+        assert!(dump.contains(&format!(
+            r#"filters.blocklist = ["war"] # file {}"#,
+            c.get_config_file_path()
+        )));
+
+        // The profile is saved
+        let c = ConfigResolver::new(locations.clone()).unwrap();
+        assert_eq!(c.get_template(), "$fact:blue");
+
+        // An environment variable overrides the profile
+        let c = c
+            .with_overrides(vec![get_env_layer(|name| match name {
+                "CULTURA_PROFILE" => Some("home".to_string()),
+                _ => None,
+            })
+            .unwrap()])
+            .map(|_| ());
+        match c {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown profile home"),
+        }
+
+        let c = ConfigResolver::new(locations).unwrap();
+        match c.use_profile(Some("home".to_string())) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown profile home"),
+        }
+        // The settings are defined in the active profile
+        c.use_profile(Some("work".to_string())).unwrap();
+        c.set_template("$fact:green".to_string()).unwrap();
+        match c.set_template("__|>__:cyan".to_string()) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "the $fact variable is missing"),
+        }
+        c.set_providers(vec!["TIL".to_string()]).unwrap();
+        assert_eq!(c.get_template(), "$fact:green");
+        assert_eq!(c.get_providers()[0].get_id(), "TIL");
+
+        c.use_profile(None).unwrap();
+        assert_eq!(c.get_template(), "$fact");
+        assert_eq!(c.get_providers().len(), 2);
+    }
+
+    #[test]
+    fn test_edit() {
+        let path = tempdir().unwrap().into_path();
+        let locations = Locations::new(&path);
+        ConfigResolver::new(locations.clone()).unwrap();
+
+        // An invalid config is never saved
+        let saved = edit(
+            &locations,
+            |p| {
+                Ok(fs::write(
+                    p,
+                    "template = \"$fact:blink\"\nproviders = []\n",
+                )?)
+            },
+            |issues| {
+                assert_eq!(
+                    issues[0].to_string(),
+                    "line 1: template: unknown style blink at position 7"
+                );
+                false
+            },
+        )
+        .unwrap();
+        assert!(!saved);
+        assert_eq!(
+            ConfigResolver::new(locations.clone())
+                .unwrap()
+                .get_template(),
+            DEFAULT_TEMPLATE
+        );
+        assert!(!locations.config_dir.join(EDITED_CONFIG_FILE_NAME).exists());
+
+        // The config is saved once fixed
+        let mut templates = vec!["$fact:red", "$fact:blink"];
+        let saved = edit(
+            &locations,
+            |p| {
+                let template = templates.pop().unwrap();
+                Ok(fs::write(
+                    p,
+                    format!("template = \"{}\"\nproviders = []\n", template),
+                )?)
+            },
+            |_| true,
+        )
+        .unwrap();
+        assert!(saved);
+        assert_eq!(
+            ConfigResolver::new(locations).unwrap().get_template(),
+            "$fact:red"
+        );
+    }
+
+    #[test]
+    fn test_invalid_config_file() {
+        let path = tempdir().unwrap().into_path();
+        let locations = Locations::new(&path);
+        fs::create_dir_all(&locations.config_dir).unwrap();
+        fs::write(
+            locations.config_dir.join(CONFIG_FILE_NAME),
+            r#"version = 1
+template = "$fact"
+
+[[providers]]
+provider = "WHATEVER"
+"#,
+        )
+        .unwrap();
+
+        match ConfigResolver::new(locations.clone()) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert!(
+                e.to_string().starts_with(&format!(
+                    "invalid config file {}:\n  line 5: unknown variant `WHATEVER`",
+                    locations.config_dir.join(CONFIG_FILE_NAME).display()
+                )),
+                "{}",
+                e
+            ),
+        }
+    }
+
+    #[test]
+    fn test_get_set_unset_values() {
+        let path = tempdir().unwrap().into_path();
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        assert_eq!(
+            c.get_value("scheduler.interval").unwrap(),
+            Some(Value::String("5m".to_string()))
+        );
+        assert_eq!(c.get_value("network.proxy").unwrap(), None);
+
+        c.set_value("scheduler.interval", "30m").unwrap();
+        c.set_value("filters.blocklist", "war, politics").unwrap();
+        c.set_value("template", "$fact:red").unwrap();
+        assert_eq!(
+            c.get_scheduler().interval,
+            std::time::Duration::from_secs(1800)
+        );
+        assert_eq!(
+            c.get_value("filters.blocklist").unwrap(),
+            Some(Value::Array(vec!["war".into(), "politics".into()]))
+        );
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        assert_eq!(c.get_template(), "$fact:red");
+        assert_eq!(c.get_filter_rules().blocklist, vec!["war", "politics"]);
+
+        c.unset_value("scheduler.interval").unwrap();
+        c.unset_value("template").unwrap();
+        assert_eq!(c.get_scheduler(), Scheduler::default());
+        assert_eq!(c.get_template(), DEFAULT_TEMPLATE);
+        let saved = fs::read_to_string(c.get_config_file_path()).unwrap();
+        assert!(!saved.contains("scheduler"), "{}", saved);
+        assert!(saved.contains("politics"), "{}", saved);
+
+        for (path, raw, error) in [
+            ("scheduler", "5m", "unknown key scheduler"),
+            ("scheduler.interval", "soon", "expected number at 0"),
+            (
+                "scheduler.interval",
+                "0s",
+                "scheduler.interval: the duration must be greater than 0",
+            ),
+            (
+                "template",
+                "$fact:blink",
+                "template: unknown style blink at position 7",
+            ),
+            ("profile", "work", "profile: unknown profile work"),
+        ] {
+            match c.set_value(path, raw) {
+                Ok(_) => panic!("must return an error"),
+                Err(e) => assert_eq!(e.to_string(), error),
+            }
+        }
+        assert_eq!(c.get_template(), DEFAULT_TEMPLATE);
+        match c.unset_value("whatever") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown key whatever"),
+        }
+    }
+
+    #[test]
+    fn test_manage_providers() {
+        let path = tempdir().unwrap().into_path();
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        c.set_providers(vec!["TIL,DYK".to_string()]).unwrap();
+        c.add_provider("STARTER").unwrap();
+
+        // The settings of a disabled provider are kept
+        fs::write(
+            c.get_config_file_path(),
+            fs::read_to_string(c.get_config_file_path())
+                .unwrap()
+                .replacen(
+                    "provider = \"TIL\"",
+                    "provider = \"TIL\"\nurl = \"http://localhost/til\"",
+                    1,
+                ),
+        )
+        .unwrap();
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        c.disable_provider("TIL").unwrap();
+        let get_ids = |c: &ConfigResolver| {
+            c.get_providers()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(get_ids(&c), vec!["DYK", "STARTER"]);
+        assert_eq!(
+            c.list_providers()
+                .iter()
+                .map(|(p, state)| (p.get_id(), *state))
+                .collect::<Vec<(String, ProviderState)>>(),
+            vec![
+                ("DYK".to_string(), ProviderState::Enabled),
+                ("STARTER".to_string(), ProviderState::Enabled),
+                ("TIL".to_string(), ProviderState::Disabled),
+            ]
+        );
+
+        let c = ConfigResolver::new(Locations::new(&path)).unwrap();
+        c.enable_provider("TIL").unwrap();
+        assert_eq!(
+            c.get_providers()[0].get_url(),
+            Some("http://localhost/til".to_string())
+        );
+        c.remove_provider("STARTER").unwrap();
+        c.remove_provider("DYK").unwrap();
+        assert_eq!(c.list_providers()[0].1, ProviderState::NotAdded);
+        assert_eq!(get_ids(&c), vec!["TIL"]);
+
+        for (result, error) in [
+            (c.add_provider("TIL"), "the provider TIL is already added"),
+            (c.add_provider("WHATEVER"), "unknown provider WHATEVER"),
+            (
+                c.enable_provider("TIL"),
+                "the provider TIL is already enabled",
+            ),
+            (c.disable_provider("DYK"), "the provider DYK is not added"),
+            (
+                c.remove_provider("TIL"),
+                "the last provider cannot be removed, disable it instead",
+            ),
+        ] {
+            match result {
+                Ok(_) => panic!("must return an error"),
+                Err(e) => assert_eq!(e.to_string(), error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_manage_providers_in_a_profile() {
+        let path = tempdir().unwrap().into_path();
+        let locations = Locations::new(&path);
+        fs::create_dir_all(&locations.config_dir).unwrap();
+        fs::write(
+            locations.config_dir.join(CONFIG_FILE_NAME),
+            r#"template = "$fact"
+profile = "work"
+
+[[providers]]
+provider = "TIL"
+
+[[providers]]
+provider = "DYK"
+
+[[profiles.work.providers]]
+provider = "DYK"
+"#,
+        )
+        .unwrap();
+        let get_ids = |c: &ConfigResolver| {
+            c.get_providers()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>()
+        };
+
+        // The list of the profile is edited, the one of the config is kept
+        let c = ConfigResolver::new(locations.clone()).unwrap();
+        c.add_provider("TIL").unwrap();
+        match c.add_provider("TIL") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "the provider TIL is already added"),
+        }
+        c.remove_provider("DYK").unwrap();
+        assert_eq!(get_ids(&c), vec!["TIL"]);
+        match c.remove_provider("TIL") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "the last provider cannot be removed, disable it instead"
+            ),
+        }
+        match c.set_providers(vec!["TIL,DYK,TIL".to_string()]) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "the provider TIL is given twice"),
+        }
+
+        let c = ConfigResolver::new(locations).unwrap();
+        assert_eq!(get_ids(&c), vec!["TIL"]);
+        c.use_profile(None).unwrap();
+        assert_eq!(get_ids(&c), vec!["TIL", "DYK"]);
     }
 }
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fact::{filter::Rules, SelectionStrategy},
+    third_part::Crawler,
+};
+
+// A named set of settings replacing the ones of the config when the profile
+// is active, a setting missing from the profile is taken from the config.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub providers: Option<Vec<Box<dyn Crawler>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection_strategy: Option<SelectionStrategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<ProfileRules>,
+}
+
+// The filters of a profile are merged rule by rule with the ones of the
+// config, a rule missing from the profile is taken from the config.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileRules {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocklist: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowlist: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_nsfw: Option<Vec<String>>,
+}
+
+impl ProfileRules {
+    pub fn apply(&self, rules: &Rules) -> Rules {
+        Rules {
+            blocklist: self.blocklist.clone().unwrap_or(rules.blocklist.clone()),
+            allowlist: self.allowlist.clone().unwrap_or(rules.allowlist.clone()),
+            min_length: self.min_length.or(rules.min_length),
+            max_length: self.max_length.or(rules.max_length),
+            skip_nsfw: self.skip_nsfw.clone().unwrap_or(rules.skip_nsfw.clone()),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::error::Error;
+
+use toml::{value::Table, Value};
+
+use crate::third_part;
+
+const ENV_PREFIX: &str = "CULTURA_";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Bool,
+    Integer,
+    Float,
+    String,
+    Duration,
+    StringList,
+    Providers,
+    Normalisation,
+}
+
+pub struct Key {
+    pub path: &'static str,
+    pub kind: Kind,
+}
+
+// Every setting of the config, the path is the dotted path of the key in the
+// config file.
+pub const KEYS: &[Key] = &[
+    Key {
+        path: "providers",
+        kind: Kind::Providers,
+    },
+    Key {
+        path: "template",
+        kind: Kind::String,
+    },
+    Key {
+        path: "disabled_providers",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "profile",
+        kind: Kind::String,
+    },
+    Key {
+        path: "selection_strategy",
+        kind: Kind::String,
+    },
+    Key {
+        path: "theme",
+        kind: Kind::String,
+    },
+    Key {
+        path: "normalisation",
+        kind: Kind::Normalisation,
+    },
+    Key {
+        path: "filters.blocklist",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "filters.allowlist",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "filters.min_length",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "filters.max_length",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "filters.skip_nsfw",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "deduplication.enabled",
+        kind: Kind::Bool,
+    },
+    Key {
+        path: "deduplication.threshold",
+        kind: Kind::Float,
+    },
+    Key {
+        path: "deduplication.shingle_size",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "network.proxy",
+        kind: Kind::String,
+    },
+    Key {
+        path: "network.no_proxy",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "network.connect_timeout",
+        kind: Kind::Duration,
+    },
+    Key {
+        path: "network.timeout",
+        kind: Kind::Duration,
+    },
+    Key {
+        path: "network.user_agent",
+        kind: Kind::String,
+    },
+    Key {
+        path: "network.root_certificates",
+        kind: Kind::StringList,
+    },
+    Key {
+        path: "harvest.concurrency",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "harvest.deadline",
+        kind: Kind::Duration,
+    },
+    Key {
+        path: "health.warn_after_failures",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "scheduler.interval",
+        kind: Kind::Duration,
+    },
+    Key {
+        path: "layout.max_width",
+        kind: Kind::Integer,
+    },
+    Key {
+        path: "layout.max_lines",
+        kind: Kind::Integer,
+    },
+];
+
+pub fn find_key(path: &str) -> Result<&'static Key, Box<dyn Error>> {
+    match KEYS.iter().find(|k| k.path == path) {
+        Some(key) => Ok(key),
+        None => Err(format!("unknown key {}", path))?,
+    }
+}
+
+pub fn get_paths() -> Vec<&'static str> {
+    KEYS.iter().map(|k| k.path).collect()
+}
+
+// CULTURA_ followed by the path in uppercase, dots replaced by underscores,
+// for instance CULTURA_HARVEST_CONCURRENCY.
+pub fn get_env_var(key: &Key) -> String {
+    format!(
+        "{}{}",
+        ENV_PREFIX,
+        key.path.to_uppercase().replace('.', "_")
+    )
+}
+
+// Convert a raw value, coming from an environment variable for instance, to
+// the type of the key. Lists are comma separated and the normalisation steps
+// are given as an inline TOML table.
+pub fn parse_value(key: &Key, raw: &str) -> Result<Value, Box<dyn Error>> {
+    let split = || {
+        raw.split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+    };
+    let value = match key.kind {
+        Kind::Bool => Value::Boolean(raw.trim().parse()?),
+        Kind::Integer => Value::Integer(raw.trim().parse::<u32>()?.into()),
+        Kind::Float => Value::Float(raw.trim().parse()?),
+        Kind::String => Value::String(raw.to_string()),
+        Kind::Duration => {
+            humantime::parse_duration(raw.trim())?;
+            Value::String(raw.trim().to_string())
+        }
+        Kind::StringList => Value::Array(split().map(Value::String).collect()),
+        Kind::Providers => {
+            let available_providers = third_part::get_available_providers();
+            let ids = split().collect::<Vec<String>>();
+            if let Some(id) = ids
+                .iter()
+                .find(|id| !available_providers.contains_key(id.as_str()))
+            {
+                Err(format!("unknown provider {}", id))?
+            }
+            Value::Array(
+                ids.into_iter()
+                    .map(|id| Value::Table(Table::from_iter([("provider".to_string(), id.into())])))
+                    .collect(),
+            )
+        }
+        Kind::Normalisation => {
+            let mut table = toml::from_str::<Table>(&format!("value = {}", raw))?;
+            match table.remove("value") {
+                Some(value @ Value::Table(_)) => value,
+                _ => Err("an inline table is expected")?,
+            }
+        }
+    };
+    Ok(value)
+}
+
+pub fn get<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |v, name| v.get(name))
+}
+
+pub fn set(value: &mut Value, path: &str, new_value: Value) {
+    let mut names = path.split('.').collect::<Vec<&str>>();
+    let last = names.pop().unwrap_or_default();
+    let mut current = value;
+    for name in names {
+        if !current.get(name).is_some_and(|v| v.is_table()) {
+            if let Some(table) = current.as_table_mut() {
+                table.insert(name.to_string(), Value::Table(Table::new()));
+            }
+        }
+        current = match current.get_mut(name) {
+            Some(v) => v,
+            None => return,
+        };
+    }
+    if let Some(table) = current.as_table_mut() {
+        table.insert(last.to_string(), new_value);
+    }
+}
+
+pub fn remove(value: &mut Value, path: &str) -> Option<Value> {
+    let (parent, name) = match path.rsplit_once('.') {
+        Some((parent, name)) => (get_mut(value, parent)?, name),
+        None => (value, path),
+    };
+    parent.as_table_mut()?.remove(name)
+}
+
+fn get_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(value, |v, name| v.get_mut(name))
+}
+
+// Merge the overlay into the base, tables are merged recursively while any
+// other value of the overlay replaces the one of the base.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (name, value) in overlay {
+                match base.get_mut(&name) {
+                    Some(v) => merge(v, value),
+                    None => {
+                        base.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_key(path: &str) -> &'static Key {
+        find_key(path).unwrap()
+    }
+
+    #[test]
+    fn test_find_key() {
+        assert_eq!(find_key("harvest.deadline").unwrap().kind, Kind::Duration);
+        match find_key("harvest") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown key harvest"),
+        }
+    }
+
+    #[test]
+    fn test_get_env_var() {
+        assert_eq!(get_env_var(get_key("template")), "CULTURA_TEMPLATE");
+        assert_eq!(
+            get_env_var(get_key("network.connect_timeout")),
+            "CULTURA_NETWORK_CONNECT_TIMEOUT"
+        );
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(
+            parse_value(get_key("deduplication.enabled"), "false").unwrap(),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            parse_value(get_key("harvest.concurrency"), "8").unwrap(),
+            Value::Integer(8)
+        );
+        assert_eq!(
+            parse_value(get_key("harvest.deadline"), "2m").unwrap(),
+            Value::String("2m".to_string())
+        );
+        assert_eq!(
+            parse_value(get_key("filters.blocklist"), "war, /^politic/").unwrap(),
+            Value::Array(vec!["war".into(), "/^politic/".into()])
+        );
+        assert_eq!(
+            parse_value(get_key("providers"), "TIL,DYK").unwrap(),
+            toml::from_str::<Table>(r#"v = [{provider = "TIL"}, {provider = "DYK"}]"#).unwrap()
+                ["v"]
+        );
+        assert_eq!(
+            parse_value(
+                get_key("normalisation"),
+                r#"{TIL = [{step = "capitalise"}]}"#
+            )
+            .unwrap(),
+            toml::from_str::<Table>(r#"v = {TIL = [{step = "capitalise"}]}"#).unwrap()["v"]
+        );
+
+        for (path, raw, error) in [
+            ("harvest.concurrency", "-1", "invalid digit found in string"),
+            ("harvest.deadline", "soon", "expected number at 0"),
+            ("providers", "TIL,WHATEVER", "unknown provider WHATEVER"),
+            ("normalisation", "[]", "an inline table is expected"),
+        ] {
+            match parse_value(get_key(path), raw) {
+                Ok(_) => panic!("must return an error"),
+                Err(e) => assert_eq!(e.to_string(), error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = toml::from_str::<Value>(
+            r#"
+template = "$fact"
+[harvest]
+concurrency = 2
+deadline = "1m"
+"#,
+        )
+        .unwrap();
+        let mut overlay = Value::Table(Table::new());
+        set(&mut overlay, "harvest.concurrency", Value::Integer(8));
+        set(
+            &mut overlay,
+            "network.timeout",
+            Value::String("5s".to_string()),
+        );
+        merge(&mut base, overlay);
+
+        assert_eq!(
+            get(&base, "template"),
+            Some(&Value::String("$fact".to_string()))
+        );
+        assert_eq!(get(&base, "harvest.concurrency"), Some(&Value::Integer(8)));
+        assert_eq!(
+            get(&base, "harvest.deadline"),
+            Some(&Value::String("1m".to_string()))
+        );
+        assert_eq!(
+            get(&base, "network.timeout"),
+            Some(&Value::String("5s".to_string()))
+        );
+        assert_eq!(get(&base, "network.proxy"), None);
+
+        assert_eq!(
+            remove(&mut base, "harvest.deadline"),
+            Some(Value::String("1m".to_string()))
+        );
+        assert_eq!(get(&base, "harvest.deadline"), None);
+        assert_eq!(get(&base, "harvest.concurrency"), Some(&Value::Integer(8)));
+        assert_eq!(remove(&mut base, "filters.blocklist"), None);
+        assert!(remove(&mut base, "template").is_some());
+        assert_eq!(get(&base, "template"), None);
+    }
+}
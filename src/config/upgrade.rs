@@ -0,0 +1,205 @@
+use std::error::Error;
+
+use toml::{value::Table, Value};
+
+use super::Config;
+
+pub type Upgrade = fn(&mut Table) -> Result<(), Box<dyn Error>>;
+
+// Each upgrade rewrites a config file from the version given by its index + 1
+// to the next one, the version of a config file is stored in its version key
+// and a file without this key, written before the versioning, is a version 1
+// file.
+pub const UPGRADES: &[Upgrade] = &[];
+
+pub const CONFIG_VERSION: usize = UPGRADES.len() + 1;
+
+// Return the content of a config file upgraded to the last version of the
+// given upgrades, UPGRADES outside of the tests, or None when it is already up
+// to date. A file which cannot be parsed is left to the validation to report
+// the issues with their line.
+pub fn upgrade(content: &str, upgrades: &[Upgrade]) -> Result<Option<String>, Box<dyn Error>> {
+    let config_version = upgrades.len() + 1;
+    let mut table = match toml::from_str::<Table>(content) {
+        Ok(table) => table,
+        Err(_) => return Ok(None),
+    };
+    let version = match table.get("version") {
+        None => 1,
+        Some(Value::Integer(version)) if *version >= 1 => *version as usize,
+        Some(_) => return Ok(None),
+    };
+    if version > config_version {
+        Err(format!(
+            "the config file version {} is newer than the supported one {}, upgrade cultura",
+            version, config_version
+        ))?
+    }
+    if version == config_version {
+        return Ok(None);
+    }
+
+    for upgrade in upgrades.iter().skip(version - 1) {
+        upgrade(&mut table)?;
+    }
+    table.insert("version".to_string(), Value::Integer(config_version as i64));
+    // Write the file the way cultura does unless it is invalid
+    let content = match Value::Table(table.clone()).try_into::<Config>() {
+        Ok(config) => toml::to_string(&config)?,
+        Err(_) => toml::to_string(&table)?,
+    };
+    Ok(Some(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::config::{load_config_file, ConfigResolver, Locations, CONFIG_FILE_NAME};
+
+    const FIXTURES: &[&str] = &[include_str!("fixtures/v1.toml")];
+    // A file of a format older than the version 1 one, upgraded by the steps
+    // below which stand for the future upgrades
+    const V0_FIXTURE: &str = include_str!("fixtures/v0.toml");
+
+    fn move_blocklist(table: &mut Table) -> Result<(), Box<dyn Error>> {
+        if let Some(blocklist) = table.remove("blocklist") {
+            match table.entry("filters").or_insert(Value::Table(Table::new())) {
+                Value::Table(filters) => filters.insert("blocklist".to_string(), blocklist),
+                _ => Err("filters must be a table")?,
+            };
+        }
+        Ok(())
+    }
+
+    fn move_blocklist_and_break_template(table: &mut Table) -> Result<(), Box<dyn Error>> {
+        move_blocklist(table)?;
+        table.insert(
+            "template".to_string(),
+            Value::String("$fact:blink".to_string()),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixtures_cover_every_version() {
+        assert_eq!(FIXTURES.len(), CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        for (version, fixture) in (1..).zip(FIXTURES) {
+            let locations = Locations::new(&tempdir().unwrap().into_path());
+            let config_file = locations.config_dir.join(CONFIG_FILE_NAME);
+            let backup_file = locations
+                .config_dir
+                .join(format!("{}.bak", CONFIG_FILE_NAME));
+            fs::create_dir_all(&locations.config_dir).unwrap();
+            fs::write(&config_file, fixture).unwrap();
+
+            let c = ConfigResolver::new(locations.clone())
+                .unwrap_or_else(|e| panic!("version {}: {}", version, e));
+            assert_eq!(
+                c.get_providers()
+                    .iter()
+                    .map(|p| p.get_id())
+                    .collect::<Vec<String>>(),
+                vec!["TIL", "DYK"],
+                "version {}",
+                version
+            );
+            assert_eq!(c.get_template(), "__|>__:cyan $fact:yellow");
+            assert_eq!(c.get_filter_rules().blocklist, vec!["politics"]);
+
+            let content = fs::read_to_string(&config_file).unwrap();
+            assert!(
+                content.starts_with(&format!("version = {}\n", CONFIG_VERSION)),
+                "version {}: {}",
+                version,
+                content
+            );
+            if version < CONFIG_VERSION {
+                assert_eq!(&fs::read_to_string(&backup_file).unwrap(), fixture);
+            } else {
+                assert_eq!(&content, fixture);
+                assert!(!backup_file.exists());
+            }
+        }
+    }
+
+    #[test]
+    fn test_upgrade_with_a_step() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join(CONFIG_FILE_NAME);
+        let backup_file = dir.path().join(format!("{}.bak", CONFIG_FILE_NAME));
+        let path = config_file.display().to_string();
+        fs::write(&config_file, V0_FIXTURE).unwrap();
+
+        let config = load_config_file(&path, &[move_blocklist]).unwrap();
+        assert_eq!(config.filters.blocklist, vec!["politics"]);
+        assert_eq!(fs::read_to_string(&backup_file).unwrap(), V0_FIXTURE);
+        let content = fs::read_to_string(&config_file).unwrap();
+        assert!(content.starts_with("version = 2\n"), "{}", content);
+        assert!(
+            content.contains("[filters]\nblocklist = [\"politics\"]\n"),
+            "{}",
+            content
+        );
+
+        // The upgraded file is loaded as it is
+        load_config_file(&path, &[move_blocklist]).unwrap();
+        assert_eq!(fs::read_to_string(&config_file).unwrap(), content);
+        assert_eq!(fs::read_to_string(&backup_file).unwrap(), V0_FIXTURE);
+    }
+
+    #[test]
+    fn test_upgrade_to_an_invalid_config() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join(CONFIG_FILE_NAME);
+        let path = config_file.display().to_string();
+        fs::write(&config_file, V0_FIXTURE).unwrap();
+
+        match load_config_file(&path, &[move_blocklist_and_break_template]) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "invalid config file {}:\n  line 2: template: unknown style blink at position 7",
+                    path
+                )
+            ),
+        }
+        // Nothing is written when the upgraded config is invalid
+        assert_eq!(fs::read_to_string(&config_file).unwrap(), V0_FIXTURE);
+        assert!(!dir
+            .path()
+            .join(format!("{}.bak", CONFIG_FILE_NAME))
+            .exists());
+    }
+
+    #[test]
+    fn test_upgrade_newer_version() {
+        match upgrade(&format!("version = {}\n", CONFIG_VERSION + 1), UPGRADES) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "the config file version {} is newer than the supported one {}, upgrade cultura",
+                    CONFIG_VERSION + 1,
+                    CONFIG_VERSION
+                )
+            ),
+        }
+        assert_eq!(upgrade("template = [", UPGRADES).unwrap(), None);
+    }
+
+    #[test]
+    fn test_upgrade_without_version() {
+        // The file is left as written, with its comments
+        let content = "# My config\ntemplate = \"$fact\" # short\n";
+        assert_eq!(upgrade(content, UPGRADES).unwrap(), None);
+    }
+}
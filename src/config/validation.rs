@@ -0,0 +1,375 @@
+use std::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use super::Config;
+use crate::{
+    fact::{self, filter::Filter, normaliser::Normaliser},
+    third_part::{self, Crawler},
+};
+
+// A problem found in a config file, the line is unknown when the key cannot
+// be located in the file.
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// Parse the content of a config file then check the values the
+// deserialization accepts but cultura cannot use.
+pub fn validate(content: &str) -> Result<Config, Vec<Issue>> {
+    let config: Config = match toml::from_str(content) {
+        Ok(config) => config,
+        Err(e) => {
+            return Err(vec![Issue {
+                line: e.span().map(|span| get_line(content, span.start)),
+                message: e.message().trim().to_string(),
+            }])
+        }
+    };
+
+    let mut issues = vec![];
+    let mut check = |path: &str, result: Result<(), String>| {
+        if let Err(message) = result {
+            issues.push(Issue {
+                line: find_line(content, path),
+                message: format!("{}: {}", path, message),
+            });
+        }
+    };
+
+    let available_providers = third_part::get_available_providers();
+    if let Some(id) = config
+        .disabled_providers
+        .iter()
+        .find(|id| !available_providers.contains_key(id.as_str()))
+    {
+        check(
+            "disabled_providers",
+            Err(format!("unknown provider {}", id)),
+        );
+    }
+    check("providers", check_providers(&config.providers));
+    check("template", check_template(&config.template));
+    if let Some(name) = &config.profile {
+        if !config.profiles.contains_key(name) {
+            check("profile", Err(format!("unknown profile {}", name)));
+        }
+    }
+    check(
+        "filters",
+        Filter::new(&config.filters)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    );
+    for (provider, steps) in &config.normalisation {
+        check(
+            &format!("normalisation.{}", provider),
+            Normaliser::new(steps)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        );
+    }
+    for (path, duration) in [
+        ("network.connect_timeout", config.network.connect_timeout),
+        ("network.timeout", config.network.timeout),
+        ("harvest.deadline", config.harvest.deadline),
+        ("scheduler.interval", config.scheduler.interval),
+    ] {
+        check(path, check_duration(duration));
+    }
+    for (path, value) in [
+        ("layout.max_width", config.layout.max_width),
+        ("layout.max_lines", config.layout.max_lines),
+    ] {
+        if value == Some(0) {
+            check(path, Err("the value must be greater than 0".to_string()));
+        }
+    }
+    if !(0.0..=1.0).contains(&config.deduplication.threshold) {
+        check(
+            "deduplication.threshold",
+            Err("the value must be between 0 and 1".to_string()),
+        );
+    }
+    for (name, profile) in &config.profiles {
+        if let Some(providers) = &profile.providers {
+            check(
+                &format!("profiles.{}.providers", name),
+                check_providers(providers),
+            );
+        }
+        if let Some(template) = &profile.template {
+            check(
+                &format!("profiles.{}.template", name),
+                check_template(template),
+            );
+        }
+        if let Some(rules) = &profile.filters {
+            check(
+                &format!("profiles.{}.filters", name),
+                Filter::new(&rules.apply(&config.filters))
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+            );
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(config)
+    } else {
+        Err(issues)
+    }
+}
+
+fn check_providers(providers: &[Box<dyn Crawler>]) -> Result<(), String> {
+    let ids = providers
+        .iter()
+        .map(|p| p.get_id())
+        .collect::<Vec<String>>();
+    match ids
+        .iter()
+        .enumerate()
+        .find(|(i, id)| ids[..*i].contains(id))
+    {
+        Some((_, id)) => Err(format!("the provider {} is defined twice", id)),
+        None => Ok(()),
+    }
+}
+
+fn check_template(template: &str) -> Result<(), String> {
+    fact::check_template(template).map_err(|e| e.to_string())
+}
+
+fn check_duration(duration: Duration) -> Result<(), String> {
+    if duration.is_zero() {
+        Err("the duration must be greater than 0".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn get_line(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+// Find the line of a key given by its dotted path, either as a key or as a
+// table header. The first of the arrays of tables is used and a key defined
+// in an inline table is reported at the line of the inline table.
+fn find_line(content: &str, path: &str) -> Option<usize> {
+    let mut table = String::new();
+    let mut parent_line = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let full_path = if line.starts_with('[') {
+            table = normalise_key(line.trim_matches(|c| c == '[' || c == ']'));
+            table.clone()
+        } else {
+            match line.split_once('=') {
+                Some((key, _)) if !line.starts_with('#') => match table.as_str() {
+                    "" => normalise_key(key),
+                    table => format!("{}.{}", table, normalise_key(key)),
+                },
+                _ => continue,
+            }
+        };
+        if full_path == path {
+            return Some(i + 1);
+        }
+        if parent_line.is_none() && path.starts_with(&format!("{}.", full_path)) {
+            parent_line = Some(i + 1);
+        }
+    }
+    parent_line
+}
+
+fn normalise_key(key: &str) -> String {
+    key.split('.')
+        .map(|k| k.trim().trim_matches(|c| c == '"' || c == '\''))
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_issues(content: &str) -> Vec<String> {
+        match validate(content) {
+            Ok(_) => vec![],
+            Err(issues) => issues.iter().map(|i| i.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let content = r#"template = "$fact:cyan"
+
+[[providers]]
+provider = "TIL"
+
+[scheduler]
+interval = "10m"
+
+[profiles.work]
+template = "$fact:blue"
+"#;
+        match validate(content) {
+            Ok(config) => assert_eq!(config.scheduler.interval, Duration::from_secs(600)),
+            Err(issues) => panic!("{:?}", issues),
+        }
+
+        assert_eq!(
+            get_issues("template = \"$fact\"\nproviders = [\n"),
+            vec!["line 3: invalid array\nexpected `]`"]
+        );
+        let issues = get_issues(
+            r#"template = "$fact"
+
+[[providers]]
+provider = "WHATEVER"
+"#,
+        );
+        assert!(
+            issues[0].starts_with("line 4: unknown variant `WHATEVER`, expected one of"),
+            "{:?}",
+            issues
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+
+[harvest]
+deadline = "soon"
+"#
+            ),
+            vec!["line 5: invalid value: string \"soon\", expected a duration"]
+        );
+        let issues = get_issues(
+            r#"template = "$fact"
+providers = []
+
+[filter]
+blocklist = ["war"]
+"#,
+        );
+        assert!(
+            issues[0].starts_with("line 4: unknown field `filter`, expected one of"),
+            "{:?}",
+            issues
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+
+[layout]
+max_widht = 80
+"#
+            ),
+            vec!["line 5: unknown field `max_widht`, expected `max_width` or `max_lines`"]
+        );
+    }
+
+    #[test]
+    fn test_validate_values() {
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact:blink"
+providers = []
+profile = "home"
+
+[filters]
+blocklist = ["/(war/"]
+
+[scheduler]
+interval = "0s"
+
+[profiles.work]
+"template" = "$fact:yelow"
+"#
+            ),
+            vec![
+                "line 1: template: unknown style blink at position 7",
+                "line 3: profile: unknown profile home",
+                "line 5: filters: regex parse error:\n    (war\n    ^\nerror: unclosed group",
+                "line 9: scheduler.interval: the duration must be greater than 0",
+                "line 12: profiles.work.template: unknown style yelow at position 7",
+            ]
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+
+[[providers]]
+provider = "TIL"
+
+[[providers]]
+provider = "TIL"
+
+[profiles.work]
+providers = [{ provider = "DYK" }, { provider = "DYK" }]
+"#
+            ),
+            vec![
+                "line 3: providers: the provider TIL is defined twice",
+                "line 10: profiles.work.providers: the provider DYK is defined twice",
+            ]
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+network = { timeout = "0s" }
+"#
+            ),
+            vec!["line 3: network.timeout: the duration must be greater than 0"]
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+
+[layout]
+max_width = 80
+max_lines = 0
+"#
+            ),
+            vec!["line 6: layout.max_lines: the value must be greater than 0"]
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+
+[deduplication]
+threshold = 1.5
+"#
+            ),
+            vec!["line 5: deduplication.threshold: the value must be between 0 and 1"]
+        );
+        assert_eq!(
+            get_issues(
+                r#"template = "$fact"
+providers = []
+
+[profiles.work]
+template = "__|>__:cyan"
+"#
+            ),
+            vec!["line 5: profiles.work.template: the $fact variable is missing"]
+        );
+    }
+}
@@ -1,15 +1,52 @@
-use std::{error::Error, fs::File, thread, time::Duration};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::{self, OpenOptions},
+    os::fd::AsRawFd,
+    thread,
+    time::Duration,
+};
 
+use chrono::Local;
 use daemonize::Daemonize;
 use nix::{
     sys::signal::{kill, Signal},
-    unistd::Pid,
+    unistd::{dup2, Pid},
 };
+use serde::{Deserialize, Serialize};
 
-const STDOUT: &str = "/dev/null";
-const STDERR: &str = "/dev/null";
 use crate::{config::ConfigResolver, fact::Fact};
 
+// The size above which the log file is moved to cultura.log.1
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scheduler {
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler {
+            interval: Duration::from_secs(300),
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn is_default(&self) -> bool {
+        *self == Scheduler::default()
+    }
+}
+
+impl Display for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interval: {}", humantime::format_duration(self.interval))
+    }
+}
+
 pub struct Daemon<'a> {
     config_resolver: &'a ConfigResolver,
     fact: &'a Fact<'a>,
@@ -24,8 +61,11 @@ impl<'a> Daemon<'a> {
     }
 
     pub fn start(&self, run_in_foreground: bool) -> Result<(), Box<dyn Error>> {
-        let stdout = File::open(STDOUT)?;
-        let stderr = File::open(STDERR)?;
+        let stdout = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.config_resolver.get_log_file())?;
+        let stderr = stdout.try_clone()?;
 
         let run = if !run_in_foreground {
             let r = Daemonize::new()
@@ -46,10 +86,19 @@ impl<'a> Daemon<'a> {
 
         if run {
             loop {
-                self.fact.update()?;
-                thread::sleep(Duration::from_secs(
-                    60 * self.config_resolver.get_scheduler_interval_as_minutes(),
-                ));
+                if !run_in_foreground {
+                    rotate_log_file(&self.config_resolver.get_log_file())?;
+                }
+                match self.fact.update() {
+                    Ok(stats) => stats
+                        .iter()
+                        .for_each(|s| println!("{} {}", Local::now().to_rfc3339(), s)),
+                    Err(e) => {
+                        eprintln!("{} harvest failed: {}", Local::now().to_rfc3339(), e);
+                        Err(e)?
+                    }
+                }
+                thread::sleep(self.config_resolver.get_scheduler().interval);
             }
         } else {
             Ok(())
@@ -64,3 +113,18 @@ impl<'a> Daemon<'a> {
         }
     }
 }
+
+// Move the log file aside once too large, the daemon output is redirected to
+// a new one.
+fn rotate_log_file(path: &str) -> Result<(), Box<dyn Error>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() > MAX_LOG_FILE_SIZE => {
+            fs::rename(path, format!("{}.1", path))?;
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            dup2(file.as_raw_fd(), 1)?;
+            dup2(file.as_raw_fd(), 2)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
@@ -1,7 +1,71 @@
-use std::error::Error;
+use std::{error::Error, sync::Mutex};
 
 use chrono::Local;
-use rusqlite::Connection;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags};
+
+use crate::{
+    fact::SelectionStrategy,
+    third_part::{starter, Item},
+};
+
+type Migration = fn(&Connection) -> Result<(), Box<dyn Error>>;
+
+// Each migration is applied once, in order, the index + 1 of the last applied
+// migration is stored in the user_version pragma of the database.
+const MIGRATIONS: &[Migration] = &[
+    create_facts_table,
+    load_starter_pack,
+    create_duplicates_table,
+    create_http_cache_table,
+    create_harvests_table,
+    add_facts_metadata,
+    add_http_cache_body,
+    create_harvests_index,
+];
+
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+// The number of harvests kept for every provider, the older ones are removed
+pub const KEPT_HARVESTS: usize = 100;
+
+pub struct FactRecord {
+    pub id: String,
+    pub fact: String,
+    pub provider: String,
+    pub source_url: Option<String>,
+    pub title: Option<String>,
+    pub created_at: String,
+}
+
+pub struct Duplicate {
+    pub fact: String,
+    pub provider: String,
+    pub original_fact: String,
+    pub original_provider: String,
+    pub similarity: f64,
+    pub created_at: String,
+}
+
+pub struct ProviderHealth {
+    pub provider: String,
+    pub attempts: usize,
+    pub successes: usize,
+    pub consecutive_failures: usize,
+    pub last_attempt_at: String,
+    pub last_duration_ms: u64,
+    pub last_found: usize,
+    pub last_inserted: usize,
+    pub last_error: Option<String>,
+}
+
+// A page kept with the validators sent by the site, the body is returned
+// when the site replies that the page didn't change
+#[derive(Clone)]
+pub struct CachedPage {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
 
 pub struct Fact {
     connection: Connection,
@@ -9,54 +73,172 @@ pub struct Fact {
 
 impl Fact {
     pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
-        Connection::open(path)
-            .map(|connection| Fact { connection })
-            .map(|f| -> Result<Self, Box<dyn Error>> {
-                f.migrate()?;
-                Ok(f)
-            })?
+        Ok(Fact {
+            connection: open(path)?,
+        })
     }
 
-    fn migrate(&self) -> Result<(), Box<dyn Error>> {
-        let query = "CREATE TABLE IF NOT EXISTS facts (id TEXT UNIQUE, fact TEXT UNIQUE, provider TEXT, was_displayed TINYINT(1), created_at TEXT);";
-        self.connection.execute(query, ())?;
-        Ok(())
+    // Return the id of every inserted fact, none when the fact already exists
+    pub fn create(
+        &self,
+        provider: String,
+        facts: Vec<Item>,
+    ) -> Vec<Result<Option<String>, Box<dyn Error>>> {
+        create_facts(&self.connection, provider, facts)
     }
 
-    pub fn create(&self, provider: String, facts: Vec<String>) -> Vec<Result<(), Box<dyn Error>>> {
-        facts
-            .into_iter()
-            .map(|f| -> Result<(), Box<dyn Error>> {
-                match self.connection.execute(
-                    "INSERT INTO facts VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(fact) DO NOTHING ;",
-                    [
-                        (uuid::Uuid::new_v4().to_string()),
-                        (f),
-                        (provider.to_owned()),
-                        (0.to_string()),
-                        (Local::now().to_string()),
-                    ],
-                ) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(e)?,
-                }
+    pub fn get_facts(&self) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let mut stmt = self.connection.prepare("SELECT id, fact FROM facts")?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+        }
+        Ok(results)
+    }
+
+    pub fn create_duplicate(
+        &self,
+        fact_id: String,
+        provider: String,
+        fact: String,
+        similarity: f64,
+    ) -> Result<usize, Box<dyn Error>> {
+        Ok(self.connection.execute(
+            "INSERT INTO duplicates VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(fact) DO NOTHING ;",
+            (
+                fact_id,
+                fact,
+                provider,
+                similarity,
+                Local::now().to_string(),
+            ),
+        )?)
+    }
+
+    pub fn get_duplicates(&self) -> Result<Vec<Duplicate>, Box<dyn Error>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT d.fact, d.provider, f.fact, f.provider, d.similarity, d.created_at FROM duplicates d INNER JOIN facts f ON f.id = d.fact_id ORDER BY d.created_at DESC",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(Duplicate {
+                fact: row.get(0)?,
+                provider: row.get(1)?,
+                original_fact: row.get(2)?,
+                original_provider: row.get(3)?,
+                similarity: row.get(4)?,
+                created_at: row.get(5)?,
             })
-            .collect::<Vec<Result<(), Box<dyn Error>>>>()
+        }
+        Ok(results)
+    }
+
+    pub fn create_harvest(
+        &self,
+        provider: String,
+        started_at: String,
+        duration_ms: u64,
+        facts_found: usize,
+        facts_inserted: usize,
+        error: Option<String>,
+    ) -> Result<usize, Box<dyn Error>> {
+        let inserted = self.connection.execute(
+            "INSERT INTO harvests VALUES (?1, ?2, ?3, ?4, ?5, ?6) ;",
+            (
+                &provider,
+                started_at,
+                duration_ms,
+                facts_found,
+                facts_inserted,
+                error,
+            ),
+        )?;
+        self.connection.execute(
+            "DELETE FROM harvests WHERE provider = ?1 AND started_at < (SELECT started_at FROM harvests WHERE provider = ?1 ORDER BY started_at DESC LIMIT 1 OFFSET ?2) ;",
+            params![provider, KEPT_HARVESTS - 1],
+        )?;
+        Ok(inserted)
     }
 
-    pub fn get_random_fact(&self) -> Result<Option<(String, String)>, Box<dyn Error>> {
+    pub fn get_providers_health(&self) -> Result<Vec<ProviderHealth>, Box<dyn Error>> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, fact FROM facts WHERE was_displayed = 0 ORDER BY created_at DESC LIMIT 1",
+            r#"SELECT
+  h.provider,
+  COUNT(*),
+  SUM(h.error IS NULL),
+  (SELECT COUNT(*) FROM harvests f WHERE f.provider = h.provider AND f.error IS NOT NULL AND f.started_at > COALESCE((SELECT MAX(s.started_at) FROM harvests s WHERE s.provider = h.provider AND s.error IS NULL), '')),
+  l.started_at,
+  l.duration_ms,
+  l.facts_found,
+  l.facts_inserted,
+  l.error
+FROM harvests h
+INNER JOIN harvests l ON l.provider = h.provider AND l.started_at = (SELECT MAX(started_at) FROM harvests m WHERE m.provider = h.provider)
+GROUP BY h.provider
+ORDER BY h.provider"#,
         )?;
         let mut rows = stmt.query([])?;
         let mut results = Vec::new();
         while let Some(row) = rows.next()? {
-            results.push(Some((
-                row.get::<usize, String>(0)?,
-                row.get::<usize, String>(1)?,
-            )))
+            results.push(ProviderHealth {
+                provider: row.get(0)?,
+                attempts: row.get(1)?,
+                successes: row.get(2)?,
+                consecutive_failures: row.get(3)?,
+                last_attempt_at: row.get(4)?,
+                last_duration_ms: row.get(5)?,
+                last_found: row.get(6)?,
+                last_inserted: row.get(7)?,
+                last_error: row.get(8)?,
+            })
         }
-        Ok(results.pop().unwrap_or(None))
+        Ok(results)
+    }
+
+    pub fn get_random_fact(
+        &self,
+        providers: &[String],
+        strategy: &SelectionStrategy,
+    ) -> Result<Option<FactRecord>, Box<dyn Error>> {
+        let mut stmt = self.connection.prepare(
+            format!(
+                "SELECT id, fact, provider, source_url, title, created_at FROM facts WHERE was_displayed = 0 AND provider IN ({}) ORDER BY {} LIMIT 1",
+                vec!["?"; providers.len()].join(", "),
+                match strategy {
+                    SelectionStrategy::Newest => "created_at DESC",
+                    SelectionStrategy::Random => "RANDOM()",
+                }
+            )
+            .as_str(),
+        )?;
+        let mut rows = stmt.query(params_from_iter(providers.iter()))?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            results.push(FactRecord {
+                id: row.get(0)?,
+                fact: row.get(1)?,
+                provider: row.get(2)?,
+                source_url: row.get(3)?,
+                title: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        }
+        Ok(results.pop())
+    }
+
+    pub fn count_unread_facts(&self, providers: &[String]) -> Result<usize, Box<dyn Error>> {
+        let count: i64 = self.connection.query_row(
+            format!(
+                "SELECT COUNT(*) FROM facts WHERE was_displayed = 0 AND provider IN ({})",
+                vec!["?"; providers.len()].join(", ")
+            )
+            .as_str(),
+            params_from_iter(providers.iter()),
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
     }
 
     pub fn mark_as_read(&self, id: String) -> Result<(), Box<dyn Error>> {
@@ -65,3 +247,172 @@ impl Fact {
         Ok(())
     }
 }
+
+// The cache is shared by the crawlers running in parallel
+pub struct HttpCache {
+    connection: Mutex<Connection>,
+}
+
+impl HttpCache {
+    pub fn new(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(HttpCache {
+            connection: Mutex::new(open(path)?),
+        })
+    }
+
+    pub fn get_page(&self, url: &str) -> Result<Option<CachedPage>, Box<dyn Error>> {
+        let connection = self.connection.lock().map_err(|e| e.to_string())?;
+        let mut stmt = connection
+            .prepare("SELECT etag, last_modified, body FROM http_cache WHERE url = ?1")?;
+        let mut rows = stmt.query([url])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(CachedPage {
+                etag: row.get(0)?,
+                last_modified: row.get(1)?,
+                body: row.get(2)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_page(&self, url: &str, page: &CachedPage) -> Result<(), Box<dyn Error>> {
+        let connection = self.connection.lock().map_err(|e| e.to_string())?;
+        connection.execute(
+            "INSERT INTO http_cache (url, etag, last_modified, updated_at, body) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(url) DO UPDATE SET etag = ?2, last_modified = ?3, updated_at = ?4, body = ?5 ;",
+            (
+                url,
+                &page.etag,
+                &page.last_modified,
+                Local::now().to_string(),
+                &page.body,
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+fn open(path: &str) -> Result<Connection, Box<dyn Error>> {
+    let connection = Connection::open(path)?;
+    migrate(&connection)?;
+    Ok(connection)
+}
+
+fn migrate(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let version = get_schema_version(connection)?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        migration(connection)?;
+        connection.pragma_update(None, "user_version", (i + 1) as i64)?;
+    }
+    Ok(())
+}
+
+// Return the schema version of an existing database without migrating it
+pub fn read_schema_version(path: &str) -> Result<usize, Box<dyn Error>> {
+    get_schema_version(&Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?)
+}
+
+fn get_schema_version(connection: &Connection) -> Result<usize, Box<dyn Error>> {
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(version as usize)
+}
+
+fn create_facts(
+    connection: &Connection,
+    provider: String,
+    facts: Vec<Item>,
+) -> Vec<Result<Option<String>, Box<dyn Error>>> {
+    facts
+        .into_iter()
+        .map(|f| -> Result<Option<String>, Box<dyn Error>> {
+            let id = uuid::Uuid::new_v4().to_string();
+            match connection.execute(
+                "INSERT INTO facts (id, fact, provider, was_displayed, created_at, source_url, title) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) ON CONFLICT(fact) DO NOTHING ;",
+                params![
+                    id,
+                    f.fact,
+                    provider,
+                    0.to_string(),
+                    Local::now().to_string(),
+                    f.source_url,
+                    f.title,
+                ],
+            ) {
+                Ok(0) => Ok(None),
+                Ok(_) => Ok(Some(id)),
+                Err(e) => Err(e)?,
+            }
+        })
+        .collect::<Vec<Result<Option<String>, Box<dyn Error>>>>()
+}
+
+fn create_facts_table(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let query = "CREATE TABLE IF NOT EXISTS facts (id TEXT UNIQUE, fact TEXT UNIQUE, provider TEXT, was_displayed TINYINT(1), created_at TEXT);";
+    connection.execute(query, ())?;
+    Ok(())
+}
+
+fn create_duplicates_table(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let query = "CREATE TABLE IF NOT EXISTS duplicates (fact_id TEXT, fact TEXT UNIQUE, provider TEXT, similarity REAL, created_at TEXT);";
+    connection.execute(query, ())?;
+    Ok(())
+}
+
+fn create_http_cache_table(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let query = "CREATE TABLE IF NOT EXISTS http_cache (url TEXT PRIMARY KEY, etag TEXT, last_modified TEXT, updated_at TEXT);";
+    connection.execute(query, ())?;
+    Ok(())
+}
+
+fn create_harvests_table(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let query = "CREATE TABLE IF NOT EXISTS harvests (provider TEXT, started_at TEXT, duration_ms INTEGER, facts_found INTEGER, facts_inserted INTEGER, error TEXT);";
+    connection.execute(query, ())?;
+    Ok(())
+}
+
+fn add_facts_metadata(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    connection.execute_batch(
+        "ALTER TABLE facts ADD COLUMN source_url TEXT; ALTER TABLE facts ADD COLUMN title TEXT;",
+    )?;
+    Ok(())
+}
+
+// The validators stored without the body cannot be used anymore, the pages
+// are downloaded again
+fn add_http_cache_body(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    connection
+        .execute_batch("ALTER TABLE http_cache ADD COLUMN body TEXT; DELETE FROM http_cache;")?;
+    Ok(())
+}
+
+// The health of the providers is computed on every prompt when warnings are
+// enabled
+fn create_harvests_index(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS harvests_provider_started_at ON harvests (provider, started_at);",
+        (),
+    )?;
+    Ok(())
+}
+
+// The starter pack is embedded in the binary so a fact can be displayed
+// before the daemon had the opportunity to harvest anything.
+// The migration inserts the facts itself as the facts table evolves with the
+// next migrations
+fn load_starter_pack(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    for fact in starter::get_starter_facts() {
+        connection.execute(
+            "INSERT INTO facts (id, fact, provider, was_displayed, created_at) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(fact) DO NOTHING ;",
+            [
+                uuid::Uuid::new_v4().to_string(),
+                fact,
+                starter::ID.to_string(),
+                0.to_string(),
+                Local::now().to_string(),
+            ],
+        )?;
+    }
+    Ok(())
+}
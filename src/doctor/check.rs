@@ -0,0 +1,470 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nix::{
+    sys::signal::kill,
+    unistd::{access, AccessFlags, Pid},
+};
+
+use crate::{
+    config::{self, ConfigResolver, Locations},
+    db, fact,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Status::Ok => "OK",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+        };
+        f.pad(s)
+    }
+}
+
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: Status, message: String, hint: Option<String>) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            message,
+            hint,
+        }
+    }
+}
+
+pub struct ChecksReport {
+    pub checks: Vec<Check>,
+}
+
+impl ChecksReport {
+    pub fn is_success(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Fail)
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut lines = vec![format!("{:<10} {:<6} {}", "Check", "Status", "Details")];
+        for c in &self.checks {
+            lines.push(format!("{:<10} {:<6} {}", c.name, c.status, c.message));
+            if let Some(hint) = &c.hint {
+                lines.push(format!("{:<17} hint: {}", "", hint));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+// Every check is run even if a previous one failed, the checks depending on
+// the config are skipped when it cannot be loaded.
+pub fn run_checks(
+    locations: &Locations,
+    config_file: Option<&str>,
+    home: Option<PathBuf>,
+    shell: Option<String>,
+) -> ChecksReport {
+    let mut checks = vec![];
+    // The config is read as it is, nothing is created nor upgraded
+    let config_resolver = match ConfigResolver::open(locations.clone())
+        .and_then(|c| c.with_overrides(config::load_overrides(config_file)?))
+    {
+        Ok(config_resolver) if !Path::new(&config_resolver.get_config_file_path()).exists() => {
+            checks.push(Check::new(
+                "config",
+                Status::Warn,
+                format!(
+                    "{} is missing, the default config is used",
+                    config_resolver.get_config_file_path()
+                ),
+                Some("run `cultura fact generate-random` to create it".to_string()),
+            ));
+            Some(config_resolver)
+        }
+        Ok(config_resolver) => {
+            checks.push(Check::new(
+                "config",
+                Status::Ok,
+                format!(
+                    "{} providers enabled",
+                    config_resolver.get_providers().len()
+                ),
+                None,
+            ));
+            Some(config_resolver)
+        }
+        Err(e) => {
+            checks.push(Check::new(
+                "config",
+                Status::Fail,
+                format!("cannot load the config: {}", e),
+                Some("fix it with `cultura config edit` or run `cultura doctor reset`".to_string()),
+            ));
+            None
+        }
+    };
+
+    match &config_resolver {
+        Some(config_resolver) => {
+            checks.push(check_database(config_resolver));
+            checks.append(&mut check_daemon(config_resolver));
+            checks.push(check_log_file(config_resolver));
+            checks.push(check_template(config_resolver));
+        }
+        None => ["database", "pid file", "daemon", "log file", "template"]
+            .iter()
+            .for_each(|name| {
+                checks.push(Check::new(
+                    name,
+                    Status::Skip,
+                    "the config cannot be loaded".to_string(),
+                    None,
+                ))
+            }),
+    }
+    checks.push(check_shell(home, shell));
+
+    ChecksReport { checks }
+}
+
+fn check_database(config_resolver: &ConfigResolver) -> Check {
+    let path = config_resolver.get_database_path();
+    if !Path::new(&path).exists() {
+        return Check::new(
+            "database",
+            Status::Warn,
+            format!("{} doesn't exist yet", path),
+            Some("run `cultura fact generate-random` to create it".to_string()),
+        );
+    }
+    match db::read_schema_version(&path) {
+        Ok(version) if version == db::SCHEMA_VERSION => Check::new(
+            "database",
+            Status::Ok,
+            format!("schema version {} is up to date", version),
+            None,
+        ),
+        Ok(version) if version < db::SCHEMA_VERSION => Check::new(
+            "database",
+            Status::Warn,
+            format!(
+                "schema version {} is outdated, {} expected",
+                version,
+                db::SCHEMA_VERSION
+            ),
+            Some("run `cultura fact generate-random` to migrate it".to_string()),
+        ),
+        Ok(version) => Check::new(
+            "database",
+            Status::Fail,
+            format!(
+                "schema version {} is newer than the supported one {}",
+                version,
+                db::SCHEMA_VERSION
+            ),
+            Some("upgrade cultura or run `cultura doctor reset`".to_string()),
+        ),
+        Err(e) => Check::new(
+            "database",
+            Status::Fail,
+            format!("cannot open {}: {}", path, e),
+            Some("run `cultura doctor reset` to recreate it".to_string()),
+        ),
+    }
+}
+
+fn check_daemon(config_resolver: &ConfigResolver) -> Vec<Check> {
+    let path = config_resolver.get_pid_file();
+    let not_running = Check::new(
+        "daemon",
+        Status::Warn,
+        "the daemon is not running".to_string(),
+        Some("run `cultura daemon start`".to_string()),
+    );
+    if !Path::new(&path).exists() {
+        return vec![
+            Check::new("pid file", Status::Ok, "no pid file".to_string(), None),
+            not_running,
+        ];
+    }
+    match config_resolver.get_daemon_pid() {
+        Ok(pid) if kill(Pid::from_raw(pid), None).is_ok() => vec![
+            Check::new("pid file", Status::Ok, path, None),
+            Check::new(
+                "daemon",
+                Status::Ok,
+                format!("running with pid {}", pid),
+                None,
+            ),
+        ],
+        Ok(pid) => vec![
+            Check::new(
+                "pid file",
+                Status::Warn,
+                format!("stale, no process is running with pid {}", pid),
+                Some(format!("remove {}", path)),
+            ),
+            not_running,
+        ],
+        Err(e) => vec![
+            Check::new(
+                "pid file",
+                Status::Fail,
+                format!("cannot read {}: {}", path, e),
+                Some(format!("remove {}", path)),
+            ),
+            Check::new(
+                "daemon",
+                Status::Skip,
+                "the pid file cannot be read".to_string(),
+                None,
+            ),
+        ],
+    }
+}
+
+fn check_log_file(config_resolver: &ConfigResolver) -> Check {
+    let path = PathBuf::from(config_resolver.get_log_file());
+    // The log file and its directories are created by the daemon, the check
+    // doesn't write anything
+    let (target, message) = match path.ancestors().find(|p| p.exists()) {
+        Some(dir) if dir != path => (dir, format!("{} can be created", path.display())),
+        _ => (path.as_path(), format!("{} is writable", path.display())),
+    };
+    match access(target, AccessFlags::W_OK) {
+        Ok(_) => Check::new("log file", Status::Ok, message, None),
+        Err(e) => Check::new(
+            "log file",
+            Status::Fail,
+            format!("cannot write to {}: {}", target.display(), e),
+            Some("ensure the config directory is writable".to_string()),
+        ),
+    }
+}
+
+fn check_template(config_resolver: &ConfigResolver) -> Check {
+    match fact::check_template(&config_resolver.get_template()) {
+        Ok(_) => Check::new(
+            "template",
+            Status::Ok,
+            "the template is valid".to_string(),
+            None,
+        ),
+        Err(e) => Check::new(
+            "template",
+            Status::Fail,
+            e.to_string(),
+            Some("define a new one with `cultura config set-template`".to_string()),
+        ),
+    }
+}
+
+fn check_shell(home: Option<PathBuf>, shell: Option<String>) -> Check {
+    let (home, shell) = match (home, shell) {
+        (Some(home), Some(shell)) => (home, shell),
+        _ => {
+            return Check::new(
+                "shell",
+                Status::Skip,
+                "cannot detect the shell".to_string(),
+                None,
+            )
+        }
+    };
+    let shell = shell.rsplit('/').next().unwrap_or_default().to_string();
+    let rc_files = match shell.as_str() {
+        "bash" => vec![
+            home.join(".bashrc"),
+            home.join(".bash_profile"),
+            home.join(".profile"),
+        ],
+        "zsh" => vec![std::env::var("ZDOTDIR")
+            .map(PathBuf::from)
+            .unwrap_or(home)
+            .join(".zshrc")],
+        "fish" => {
+            let fish_dir = home.join(".config/fish");
+            let mut files = vec![fish_dir.join("config.fish")];
+            if let Ok(entries) = fs::read_dir(fish_dir.join("conf.d")) {
+                files.extend(entries.flatten().map(|e| e.path()));
+            }
+            files
+        }
+        _ => {
+            return Check::new(
+                "shell",
+                Status::Skip,
+                format!("the shell {} is not supported", shell),
+                None,
+            )
+        }
+    };
+
+    // The docker scripts are accepted as well as they wrap the init command
+    let snippets = [
+        format!("cultura init {}", shell),
+        format!("docker.{}", shell),
+    ];
+    match rc_files.iter().find(|path| {
+        fs::read_to_string(path)
+            .map(|content| snippets.iter().any(|s| content.contains(s.as_str())))
+            .unwrap_or(false)
+    }) {
+        Some(path) => Check::new(
+            "shell",
+            Status::Ok,
+            format!("the init snippet is installed in {}", path.display()),
+            None,
+        ),
+        None => Check::new(
+            "shell",
+            Status::Warn,
+            format!("the init snippet is missing from the {} config", shell),
+            Some(format!(
+                "add the output of `cultura init {}` to your shell config, see the README",
+                shell
+            )),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn get_status(report: &ChecksReport, name: &str) -> Status {
+        match report.checks.iter().find(|c| c.name == name) {
+            Some(c) => c.status,
+            None => panic!("check {} not found", name),
+        }
+    }
+
+    #[test]
+    fn test_run_checks_on_a_fresh_install() {
+        let dir = tempdir().unwrap();
+        let report = run_checks(
+            &Locations::new(dir.path()),
+            None,
+            Some(dir.path().to_path_buf()),
+            Some("/bin/bash".to_string()),
+        );
+
+        assert!(report.is_success());
+        assert_eq!(get_status(&report, "config"), Status::Warn);
+        assert_eq!(get_status(&report, "database"), Status::Warn);
+        assert_eq!(get_status(&report, "pid file"), Status::Ok);
+        assert_eq!(get_status(&report, "daemon"), Status::Warn);
+        assert_eq!(get_status(&report, "log file"), Status::Ok);
+        assert_eq!(get_status(&report, "template"), Status::Ok);
+        assert_eq!(get_status(&report, "shell"), Status::Warn);
+        // Nothing is written by the checks
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_run_checks_on_a_configured_install() {
+        let dir = tempdir().unwrap();
+        let config_resolver = ConfigResolver::new(Locations::new(dir.path())).unwrap();
+        db::Fact::new(&config_resolver.get_database_path()).unwrap();
+        fs::write(
+            config_resolver.get_pid_file(),
+            std::process::id().to_string(),
+        )
+        .unwrap();
+        fs::write(dir.path().join(".bashrc"), "source <(cultura init bash)\n").unwrap();
+
+        let report = run_checks(
+            &Locations::new(dir.path()),
+            None,
+            Some(dir.path().to_path_buf()),
+            Some("bash".to_string()),
+        );
+
+        assert!(report.is_success());
+        assert!(report
+            .checks
+            .iter()
+            .filter(|c| c.name != "config")
+            .all(|c| c.status == Status::Ok));
+    }
+
+    #[test]
+    fn test_run_checks_on_a_broken_install() {
+        let dir = tempdir().unwrap();
+        let config_resolver = ConfigResolver::new(Locations::new(dir.path())).unwrap();
+        fs::write(config_resolver.get_pid_file(), "not a pid").unwrap();
+        // The overrides are not validated like the config file
+        let cli_file = dir.path().join("cli.toml");
+        fs::write(&cli_file, "template = \"__|>__:blink\"\n").unwrap();
+
+        let report = run_checks(
+            &Locations::new(dir.path()),
+            cli_file.to_str(),
+            Some(dir.path().to_path_buf()),
+            Some("fish".to_string()),
+        );
+        assert!(!report.is_success());
+        assert_eq!(get_status(&report, "pid file"), Status::Fail);
+        assert_eq!(get_status(&report, "daemon"), Status::Skip);
+        assert_eq!(get_status(&report, "template"), Status::Fail);
+
+        fs::write(config_resolver.get_config_file_path(), "template = [").unwrap();
+        let report = run_checks(
+            &Locations::new(dir.path()),
+            None,
+            Some(dir.path().to_path_buf()),
+            Some("fish".to_string()),
+        );
+        assert!(!report.is_success());
+        assert_eq!(get_status(&report, "config"), Status::Fail);
+        assert_eq!(get_status(&report, "database"), Status::Skip);
+        assert_eq!(get_status(&report, "template"), Status::Skip);
+    }
+
+    #[test]
+    fn test_checks_report_output() {
+        let report = ChecksReport {
+            checks: vec![
+                Check::new(
+                    "config",
+                    Status::Ok,
+                    "3 providers enabled".to_string(),
+                    None,
+                ),
+                Check::new(
+                    "daemon",
+                    Status::Warn,
+                    "the daemon is not running".to_string(),
+                    Some("run `cultura daemon start`".to_string()),
+                ),
+            ],
+        };
+
+        assert_eq!(
+            report.to_table(),
+            r#"Check      Status Details
+config     OK     3 providers enabled
+daemon     WARN   the daemon is not running
+                  hint: run `cultura daemon start`"#
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{error::Error, fs, sync::Arc, time::Duration};
+
+use serde::Serialize;
+
+use crate::{
+    fact::harvest::{self, Harvest},
+    third_part::{self, http, Crawler},
+};
+
+pub mod check;
+
+const SAMPLES_COUNT: usize = 3;
+
+#[derive(Serialize)]
+pub struct ProviderReport {
+    pub provider: String,
+    pub success: bool,
+    pub facts_count: usize,
+    pub duration_ms: u64,
+    pub samples: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ProvidersReport {
+    pub providers: Vec<ProviderReport>,
+}
+
+impl ProvidersReport {
+    pub fn is_success(&self) -> bool {
+        self.providers.iter().all(|p| p.success)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<10} {:<6} {:>6} {:>10}",
+            "Provider", "Status", "Facts", "Duration"
+        )];
+        lines.extend(self.providers.iter().map(|p| {
+            format!(
+                "{:<10} {:<6} {:>6} {:>8}ms",
+                p.provider,
+                if p.success { "PASS" } else { "FAIL" },
+                p.facts_count,
+                p.duration_ms
+            )
+        }));
+        for p in &self.providers {
+            lines.push(String::new());
+            lines.push(format!("Provider {}", p.provider));
+            match &p.error {
+                Some(e) => {
+                    lines.push("  Error".to_string());
+                    lines.push(format!("    {}", e));
+                }
+                None => {
+                    lines.push("  Samples".to_string());
+                    lines.extend(p.samples.iter().map(|s| format!("    {}", s)));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+// Return the available providers matching the given ids, all of them when
+// no id is provided.
+pub fn select_providers(ids: &[String]) -> Result<Vec<Box<dyn Crawler>>, Box<dyn Error>> {
+    let available_providers = third_part::get_available_providers();
+    let unknown_providers = ids
+        .iter()
+        .filter(|id| !available_providers.contains_key(id.as_str()))
+        .cloned()
+        .collect::<Vec<String>>();
+    if !unknown_providers.is_empty() {
+        Err(format!(
+            "unknown providers: {}",
+            unknown_providers.join(", ")
+        ))?
+    }
+
+    let mut providers = available_providers
+        .into_iter()
+        .filter(|(id, _)| ids.is_empty() || ids.contains(id))
+        .collect::<Vec<(String, Box<dyn Crawler>)>>();
+    providers.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(providers.into_iter().map(|(_, p)| p).collect())
+}
+
+// Every provider is run once, a provider finding no fact is considered as
+// failing as the parsing of its page is likely broken.
+pub fn run_providers(
+    providers: &[Box<dyn Crawler>],
+    client: Arc<http::Client>,
+    timeout: Duration,
+) -> ProvidersReport {
+    ProvidersReport {
+        providers: harvest::run(
+            providers,
+            client,
+            &Harvest {
+                concurrency: providers.len(),
+                deadline: timeout,
+            },
+        )
+        .into_iter()
+        .map(|outcome| {
+            let duration_ms = outcome.duration.as_millis() as u64;
+            match outcome.facts {
+                Ok(facts) if facts.is_empty() => ProviderReport {
+                    provider: outcome.provider,
+                    success: false,
+                    facts_count: 0,
+                    duration_ms,
+                    samples: vec![],
+                    error: Some("no fact found".to_string()),
+                },
+                Ok(facts) => ProviderReport {
+                    provider: outcome.provider,
+                    success: true,
+                    facts_count: facts.len(),
+                    duration_ms,
+                    samples: facts
+                        .into_iter()
+                        .take(SAMPLES_COUNT)
+                        .map(|i| i.fact)
+                        .collect(),
+                    error: None,
+                },
+                Err(e) => ProviderReport {
+                    provider: outcome.provider,
+                    success: false,
+                    facts_count: 0,
+                    duration_ms,
+                    samples: vec![],
+                    error: Some(e),
+                },
+            }
+        })
+        .collect(),
+    }
+}
+
+// Save the page crawled by a provider so it can be used as a fixture in the
+// tests of its parser, the size of the page is returned.
+pub fn record_fixture(
+    provider: &dyn Crawler,
+    client: &http::Client,
+    path: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let url = match provider.get_url() {
+        Some(url) => url,
+        None => Err(format!(
+            "the provider {} doesn't crawl any page",
+            provider.get_id()
+        ))?,
+    };
+    let page = client.get(&url)?;
+    fs::write(path, &page)?;
+    Ok(page.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::third_part::Item;
+
+    #[derive(Serialize, Deserialize, Clone)]
+    struct CrawlerMock {
+        id: String,
+        facts: Vec<String>,
+        sleep: u64,
+    }
+
+    #[typetag::serde]
+    impl Crawler for CrawlerMock {
+        fn get_facts(&self, _: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+            std::thread::sleep(Duration::from_millis(self.sleep));
+            Ok(self.facts.iter().map(|f| f.as_str().into()).collect())
+        }
+
+        fn get_id(&self) -> String {
+            self.id.to_owned()
+        }
+
+        fn get_description(&self) -> String {
+            "A mock".to_string()
+        }
+    }
+
+    fn get_providers() -> Vec<Box<dyn Crawler>> {
+        vec![
+            Box::new(CrawlerMock {
+                id: "working".to_string(),
+                facts: (1..=5).map(|i| format!("fact {}", i)).collect(),
+                sleep: 0,
+            }),
+            Box::new(CrawlerMock {
+                id: "empty".to_string(),
+                facts: vec![],
+                sleep: 0,
+            }),
+            Box::new(CrawlerMock {
+                id: "slow".to_string(),
+                facts: vec!["fact".to_string()],
+                sleep: 3000,
+            }),
+        ]
+    }
+
+    fn get_client() -> Arc<http::Client> {
+        Arc::new(http::Client::new(&http::Network::default(), None).unwrap())
+    }
+
+    #[test]
+    fn test_run_providers() {
+        let report = run_providers(&get_providers(), get_client(), Duration::from_millis(300));
+        assert!(!report.is_success());
+
+        let p = &report.providers[0];
+        assert_eq!(p.provider, "working");
+        assert!(p.success);
+        assert_eq!(p.facts_count, 5);
+        assert_eq!(p.samples, vec!["fact 1", "fact 2", "fact 3"]);
+        assert_eq!(p.error, None);
+
+        let p = &report.providers[1];
+        assert_eq!(p.provider, "empty");
+        assert!(!p.success);
+        assert_eq!(p.error, Some("no fact found".to_string()));
+
+        let p = &report.providers[2];
+        assert_eq!(p.provider, "slow");
+        assert!(!p.success);
+        assert_eq!(p.error, Some("deadline of 300ms exceeded".to_string()));
+
+        let providers = get_providers().into_iter().take(1).collect::<Vec<_>>();
+        assert!(run_providers(&providers, get_client(), Duration::from_millis(300)).is_success());
+    }
+
+    #[test]
+    fn test_providers_report_output() {
+        let report = ProvidersReport {
+            providers: vec![
+                ProviderReport {
+                    provider: "TIL".to_string(),
+                    success: true,
+                    facts_count: 2,
+                    duration_ms: 120,
+                    samples: vec!["fact 1".to_string(), "fact 2".to_string()],
+                    error: None,
+                },
+                ProviderReport {
+                    provider: "DYK".to_string(),
+                    success: false,
+                    facts_count: 0,
+                    duration_ms: 30,
+                    samples: vec![],
+                    error: Some("an error".to_string()),
+                },
+            ],
+        };
+
+        assert_eq!(
+            report.to_table(),
+            r#"Provider   Status  Facts   Duration
+TIL        PASS        2      120ms
+DYK        FAIL        0       30ms
+
+Provider TIL
+  Samples
+    fact 1
+    fact 2
+
+Provider DYK
+  Error
+    an error"#
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["providers"][0]["provider"], "TIL");
+        assert_eq!(json["providers"][0]["success"], true);
+        assert_eq!(json["providers"][0]["samples"][1], "fact 2");
+        assert_eq!(json["providers"][1]["error"], "an error");
+    }
+
+    #[test]
+    fn test_record_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("til.html").display().to_string();
+        let provider =
+            third_part::reddit::TIL::with_url(third_part::testing::serve("<html></html>", 1));
+
+        assert_eq!(record_fixture(&provider, &get_client(), &path).unwrap(), 13);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<html></html>");
+
+        match record_fixture(&third_part::starter::Starter::new(), &get_client(), &path) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "the provider STARTER doesn't crawl any page"),
+        }
+    }
+
+    #[test]
+    fn test_select_providers() {
+        assert_eq!(
+            select_providers(&[])
+                .unwrap()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>(),
+            vec!["DYK", "STARTER", "TIL"]
+        );
+        assert_eq!(
+            select_providers(&["TIL".to_string()])
+                .unwrap()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<String>>(),
+            vec!["TIL"]
+        );
+        match select_providers(&["TIL".to_string(), "whatever".to_string()]) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown providers: whatever"),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Providers prefix their facts with the same sentences, they must not be
+// taken in account when comparing two facts.
+const BOILERPLATES: &[&[&str]] = &[
+    &["today", "i", "learned", "that"],
+    &["today", "i", "learned"],
+    &["did", "you", "know", "that"],
+    &["did", "you", "know"],
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Deduplication {
+    pub enabled: bool,
+    pub threshold: f64,
+    pub shingle_size: usize,
+}
+
+impl Default for Deduplication {
+    fn default() -> Self {
+        Deduplication {
+            enabled: true,
+            threshold: 0.6,
+            shingle_size: 2,
+        }
+    }
+}
+
+impl Deduplication {
+    pub fn is_default(&self) -> bool {
+        *self == Deduplication::default()
+    }
+}
+
+impl Display for Deduplication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "enabled: {}, threshold: {}, shingle_size: {}",
+            self.enabled, self.threshold, self.shingle_size
+        )
+    }
+}
+
+pub struct Match {
+    pub id: String,
+    pub similarity: f64,
+}
+
+pub struct Deduplicator {
+    settings: Deduplication,
+    facts: Vec<(String, String, HashSet<String>)>,
+}
+
+impl Deduplicator {
+    pub fn new(settings: &Deduplication, facts: Vec<(String, String)>) -> Deduplicator {
+        Deduplicator {
+            settings: settings.clone(),
+            facts: if settings.enabled {
+                facts
+                    .into_iter()
+                    .map(|(id, fact)| {
+                        let shingles = shingle(&fact, settings.shingle_size);
+                        (id, fact, shingles)
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    // Index a stored fact so the next facts are compared with it as well
+    pub fn add(&mut self, id: String, fact: String) {
+        if self.settings.enabled {
+            let shingles = shingle(&fact, self.settings.shingle_size);
+            self.facts.push((id, fact, shingles));
+        }
+    }
+
+    // Return the most similar fact above the threshold, a fact strictly
+    // identical to an existing one is not reported as it is never stored twice.
+    pub fn find(&self, fact: &str) -> Option<Match> {
+        if !self.settings.enabled || self.facts.iter().any(|(_, f, _)| f == fact) {
+            return None;
+        }
+        let shingles = shingle(fact, self.settings.shingle_size);
+        self.facts
+            .iter()
+            .map(|(id, _, s)| Match {
+                id: id.to_owned(),
+                similarity: similarity(&shingles, s),
+            })
+            .filter(|m| m.similarity >= self.settings.threshold)
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let tokens = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect::<Vec<String>>();
+
+    match BOILERPLATES
+        .iter()
+        .find(|b| tokens.len() >= b.len() && tokens.iter().zip(b.iter()).all(|(t, w)| t == w))
+    {
+        Some(b) => tokens.into_iter().skip(b.len()).collect(),
+        None => tokens,
+    }
+}
+
+fn shingle(text: &str, size: usize) -> HashSet<String> {
+    let tokens = tokenize(text);
+    if tokens.len() <= size.max(1) {
+        return HashSet::from([tokens.join(" ")]);
+    }
+    tokens.windows(size.max(1)).map(|w| w.join(" ")).collect()
+}
+
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let deduplicator = Deduplicator::new(
+            &Deduplication::default(),
+            vec![
+                (
+                    "1".to_string(),
+                    "Today I learned that the Eiffel Tower can be 15 cm taller during the summer due to thermal expansion".to_string(),
+                ),
+                (
+                    "2".to_string(),
+                    "Did you know that octopuses have three hearts and blue blood?".to_string(),
+                ),
+            ],
+        );
+
+        let m = deduplicator
+            .find("Did you know that the Eiffel Tower can be 15 cm taller during the summer because of thermal expansion?")
+            .unwrap();
+        assert_eq!(m.id, "1");
+        assert!(
+            m.similarity >= 0.6 && m.similarity < 1.0,
+            "{}",
+            m.similarity
+        );
+
+        let m = deduplicator
+            .find("Did you know that the Eiffel Tower can be 15 cm taller during the summer due to thermal expansion?")
+            .unwrap();
+        assert_eq!(m.id, "1");
+        assert_eq!(m.similarity, 1.0);
+
+        assert!(deduplicator
+            .find("Did you know that octopuses have three hearts and blue blood?")
+            .is_none());
+        assert!(deduplicator
+            .find("Today I learned that koalas have fingerprints close to human ones")
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_when_disabled() {
+        let deduplicator = Deduplicator::new(
+            &Deduplication {
+                enabled: false,
+                ..Deduplication::default()
+            },
+            vec![("1".to_string(), "octopuses have three hearts".to_string())],
+        );
+
+        assert!(deduplicator.find("Octopuses have three hearts!").is_none());
+    }
+
+    #[test]
+    fn test_add() {
+        let mut deduplicator = Deduplicator::new(&Deduplication::default(), vec![]);
+        assert!(deduplicator
+            .find("Did you know that octopuses have three hearts and blue blood?")
+            .is_none());
+
+        deduplicator.add(
+            "1".to_string(),
+            "Did you know that octopuses have three hearts and blue blood?".to_string(),
+        );
+        let m = deduplicator
+            .find("Today I learned that octopuses have three hearts and a blue blood")
+            .unwrap();
+        assert_eq!(m.id, "1");
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(
+            similarity(
+                &shingle("Did you know that cats sleep a lot", 3),
+                &shingle("Today I learned that cats sleep a lot", 3)
+            ),
+            1.0
+        );
+        assert_eq!(
+            similarity(&shingle("cats sleep", 3), &shingle("dogs bark", 3)),
+            0.0
+        );
+    }
+}
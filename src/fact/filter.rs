@@ -0,0 +1,193 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+const NSFW_PATTERN: &str =
+    r"\b(?:nsfw|nsfl|porn\w*|sex|sexual\w*|nude\w*|naked|erotic\w*|gore|explicit)\b";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub blocklist: Vec<String>,
+    pub allowlist: Vec<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub skip_nsfw: Vec<String>,
+}
+
+impl Rules {
+    pub fn is_empty(&self) -> bool {
+        *self == Rules::default()
+    }
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "blocklist: {:?}, allowlist: {:?}, min_length: {}, max_length: {}, skip_nsfw: {:?}",
+            self.blocklist,
+            self.allowlist,
+            self.min_length
+                .map(|l| l.to_string())
+                .unwrap_or("none".to_string()),
+            self.max_length
+                .map(|l| l.to_string())
+                .unwrap_or("none".to_string()),
+            self.skip_nsfw,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    Blocklisted(String),
+    NotAllowlisted,
+    TooShort,
+    TooLong,
+    Nsfw,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Blocklisted(rule) => write!(f, "matches the blocklist entry {}", rule),
+            Rejection::NotAllowlisted => write!(f, "matches no allowlist entry"),
+            Rejection::TooShort => write!(f, "is too short"),
+            Rejection::TooLong => write!(f, "is too long"),
+            Rejection::Nsfw => write!(f, "is not safe for work"),
+        }
+    }
+}
+
+pub struct Filter {
+    rules: Rules,
+    blocklist: Vec<(String, Regex)>,
+    allowlist: Vec<Regex>,
+    nsfw: Regex,
+}
+
+impl Filter {
+    // A blocklist or allowlist entry is either a keyword matched case
+    // insensitively on word boundaries, or a regex when enclosed in slashes
+    // like /^Today I learned/.
+    pub fn new(rules: &Rules) -> Result<Filter, Box<dyn Error>> {
+        Ok(Filter {
+            rules: rules.clone(),
+            blocklist: rules
+                .blocklist
+                .iter()
+                .map(|e| Ok((e.to_owned(), compile_entry(e)?)))
+                .collect::<Result<Vec<(String, Regex)>, Box<dyn Error>>>()?,
+            allowlist: rules
+                .allowlist
+                .iter()
+                .map(|e| compile_entry(e))
+                .collect::<Result<Vec<Regex>, Box<dyn Error>>>()?,
+            nsfw: RegexBuilder::new(NSFW_PATTERN)
+                .case_insensitive(true)
+                .build()?,
+        })
+    }
+
+    pub fn check(&self, provider: &str, fact: &str) -> Result<(), Rejection> {
+        let length = fact.chars().count();
+        if self.rules.min_length.is_some_and(|l| length < l) {
+            return Err(Rejection::TooShort);
+        }
+        if self.rules.max_length.is_some_and(|l| length > l) {
+            return Err(Rejection::TooLong);
+        }
+        if let Some((entry, _)) = self.blocklist.iter().find(|(_, r)| r.is_match(fact)) {
+            return Err(Rejection::Blocklisted(entry.to_owned()));
+        }
+        if !self.allowlist.is_empty() && !self.allowlist.iter().any(|r| r.is_match(fact)) {
+            return Err(Rejection::NotAllowlisted);
+        }
+        if self.rules.skip_nsfw.iter().any(|p| p == provider) && self.nsfw.is_match(fact) {
+            return Err(Rejection::Nsfw);
+        }
+        Ok(())
+    }
+}
+
+fn compile_entry(entry: &str) -> Result<Regex, Box<dyn Error>> {
+    if entry.len() > 1 && entry.starts_with('/') && entry.ends_with('/') {
+        Ok(Regex::new(&entry[1..entry.len() - 1])?)
+    } else {
+        Ok(
+            RegexBuilder::new(format!(r"\b{}\b", regex::escape(entry)).as_str())
+                .case_insensitive(true)
+                .build()?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let filter = Filter::new(&Rules {
+            blocklist: vec![
+                "murder".to_string(),
+                "/^Today I learned that a?$/".to_string(),
+            ],
+            allowlist: vec![],
+            min_length: Some(10),
+            max_length: Some(60),
+            skip_nsfw: vec!["TIL".to_string()],
+        })
+        .unwrap();
+
+        let scenarios = vec![
+            ("TIL", "Today I learned that cats sleep a lot", Ok(())),
+            ("TIL", "too short", Err(Rejection::TooShort)),
+            (
+                "TIL",
+                "Today I learned that this fact is definitely way too long to be displayed",
+                Err(Rejection::TooLong),
+            ),
+            (
+                "TIL",
+                "Today I learned about a MURDER case",
+                Err(Rejection::Blocklisted("murder".to_string())),
+            ),
+            ("TIL", "Today I learned about murderers", Ok(())),
+            (
+                "TIL",
+                "Today I learned that a",
+                Err(Rejection::Blocklisted(
+                    "/^Today I learned that a?$/".to_string(),
+                )),
+            ),
+            ("TIL", "Today I learned a NSFW thing", Err(Rejection::Nsfw)),
+            ("DYK", "Did you know a NSFW thing", Ok(())),
+        ];
+
+        for (provider, fact, expected) in scenarios {
+            assert_eq!(filter.check(provider, fact), expected, "fact = {}", fact);
+        }
+    }
+
+    #[test]
+    fn test_check_with_an_allowlist() {
+        let filter = Filter::new(&Rules {
+            allowlist: vec!["rust".to_string(), "/[0-9]{4}/".to_string()],
+            ..Rules::default()
+        })
+        .unwrap();
+
+        assert_eq!(filter.check("TIL", "Rust is a fungus"), Ok(()));
+        assert_eq!(filter.check("TIL", "Built in 1889"), Ok(()));
+        assert_eq!(
+            filter.check("TIL", "Cats sleep a lot"),
+            Err(Rejection::NotAllowlisted)
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::third_part::{http, Crawler, Item};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Harvest {
+    pub concurrency: usize,
+    #[serde(with = "humantime_serde")]
+    pub deadline: Duration,
+}
+
+impl Default for Harvest {
+    fn default() -> Self {
+        Harvest {
+            concurrency: 4,
+            deadline: Duration::from_secs(120),
+        }
+    }
+}
+
+impl Harvest {
+    pub fn is_default(&self) -> bool {
+        *self == Harvest::default()
+    }
+}
+
+impl Display for Harvest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "concurrency: {}, deadline: {}",
+            self.concurrency,
+            humantime::format_duration(self.deadline)
+        )
+    }
+}
+
+pub struct Outcome {
+    pub provider: String,
+    pub started_at: DateTime<Local>,
+    pub duration: Duration,
+    pub facts: Result<Vec<Item>, String>,
+}
+
+// Run the crawlers concurrently, a crawler still running after the deadline
+// is left behind and reported as failed. Its thread keeps its slot until it
+// ends so no more than the concurrency limit of threads run at once, a
+// crawler waiting longer than the deadline for a slot is reported as failed.
+// The outcomes are returned in the order of the crawlers.
+pub fn run(
+    services: &[Box<dyn Crawler>],
+    client: Arc<http::Client>,
+    settings: &Harvest,
+) -> Vec<Outcome> {
+    let (sender, receiver) = mpsc::channel::<(usize, Result<Vec<Item>, String>)>();
+    let mut outcomes: Vec<Option<Outcome>> = services.iter().map(|_| None).collect();
+    let mut pending = services.iter().enumerate().peekable();
+    let mut running: HashMap<usize, (DateTime<Local>, Instant)> = HashMap::new();
+    // The threads alive, the ones left behind included
+    let alive = Arc::new(AtomicUsize::new(0));
+    let mut waiting_since: Option<Instant> = None;
+
+    loop {
+        while alive.load(Ordering::SeqCst) < settings.concurrency.max(1) {
+            match pending.next() {
+                Some((index, service)) => {
+                    let service = dyn_clone::clone_box(&**service);
+                    let client = client.clone();
+                    let sender = sender.clone();
+                    let alive = alive.clone();
+                    alive.fetch_add(1, Ordering::SeqCst);
+                    running.insert(index, (Local::now(), Instant::now()));
+                    thread::spawn(move || {
+                        let facts = service.get_facts(&client).map_err(|e| e.to_string());
+                        alive.fetch_sub(1, Ordering::SeqCst);
+                        // The receiver is gone once every outcome is known
+                        let _ = sender.send((index, facts));
+                    });
+                }
+                None => break,
+            }
+        }
+
+        // Only the threads left behind are running, the next crawler waits
+        // for one of them to end
+        let next_deadline = match running.values().map(|(_, start)| start).min() {
+            Some(start) => *start + settings.deadline,
+            None if pending.peek().is_some() => {
+                *waiting_since.get_or_insert_with(Instant::now) + settings.deadline
+            }
+            None => break,
+        };
+
+        match receiver.recv_timeout(next_deadline.saturating_duration_since(Instant::now())) {
+            Ok((index, facts)) => {
+                waiting_since = None;
+                if let Some((started_at, start)) = running.remove(&index) {
+                    outcomes[index] = Some(Outcome {
+                        provider: services[index].get_id(),
+                        started_at,
+                        duration: start.elapsed(),
+                        facts,
+                    });
+                }
+            }
+            Err(_) if running.is_empty() => {
+                for (index, service) in pending.by_ref() {
+                    outcomes[index] = Some(Outcome {
+                        provider: service.get_id(),
+                        started_at: Local::now(),
+                        duration: Duration::ZERO,
+                        facts: Err(format!(
+                            "not started, the providers past the deadline of {} are still running",
+                            humantime::format_duration(settings.deadline)
+                        )),
+                    });
+                }
+            }
+            Err(_) => running
+                .clone()
+                .into_iter()
+                .filter(|(_, (_, start))| start.elapsed() >= settings.deadline)
+                .for_each(|(index, (started_at, start))| {
+                    running.remove(&index);
+                    outcomes[index] = Some(Outcome {
+                        provider: services[index].get_id(),
+                        started_at,
+                        duration: start.elapsed(),
+                        facts: Err(format!(
+                            "deadline of {} exceeded",
+                            humantime::format_duration(settings.deadline)
+                        )),
+                    });
+                }),
+        }
+    }
+
+    outcomes.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone)]
+    struct SleepingCrawlerMock {
+        id: String,
+        sleep: u64,
+    }
+
+    #[typetag::serde]
+    impl Crawler for SleepingCrawlerMock {
+        fn get_facts(&self, _: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+            thread::sleep(Duration::from_millis(self.sleep));
+            if self.id == "failing" {
+                return Err("an error occurred".into());
+            }
+            Ok(vec![format!("a fact from {}", self.id).into()])
+        }
+
+        fn get_id(&self) -> String {
+            self.id.to_owned()
+        }
+
+        fn get_description(&self) -> String {
+            "A mock".to_string()
+        }
+    }
+
+    fn get_services(ids: &[&str], sleep: u64) -> Vec<Box<dyn Crawler>> {
+        ids.iter()
+            .map(|id| -> Box<dyn Crawler> {
+                Box::new(SleepingCrawlerMock {
+                    id: id.to_string(),
+                    sleep,
+                })
+            })
+            .collect()
+    }
+
+    fn get_client() -> Arc<http::Client> {
+        Arc::new(http::Client::new(&http::Network::default(), None).unwrap())
+    }
+
+    #[test]
+    fn test_run_concurrently() {
+        let services = get_services(&["p1", "p2", "p3", "p4"], 300);
+
+        let start = Instant::now();
+        let outcomes = run(
+            &services,
+            get_client(),
+            &Harvest {
+                concurrency: 4,
+                ..Harvest::default()
+            },
+        );
+        assert!(start.elapsed() < Duration::from_millis(900));
+
+        assert_eq!(
+            outcomes
+                .iter()
+                .map(|o| (o.provider.to_owned(), o.facts.clone().unwrap()))
+                .collect::<Vec<(String, Vec<Item>)>>(),
+            vec!["p1", "p2", "p3", "p4"]
+                .into_iter()
+                .map(|p| (p.to_string(), vec![format!("a fact from {}", p).into()]))
+                .collect::<Vec<(String, Vec<Item>)>>()
+        );
+        assert!(outcomes
+            .iter()
+            .all(|o| o.duration >= Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_run_with_a_concurrency_limit() {
+        let services = get_services(&["p1", "p2", "p3"], 200);
+
+        let start = Instant::now();
+        let outcomes = run(
+            &services,
+            get_client(),
+            &Harvest {
+                concurrency: 1,
+                ..Harvest::default()
+            },
+        );
+        assert!(start.elapsed() >= Duration::from_millis(600));
+        assert_eq!(outcomes.len(), 3);
+    }
+
+    #[test]
+    fn test_run_with_a_deadline() {
+        let mut services = get_services(&["slow"], 3000);
+        services.append(&mut get_services(&["fast", "failing"], 10));
+
+        let start = Instant::now();
+        let outcomes = run(
+            &services,
+            get_client(),
+            &Harvest {
+                concurrency: 4,
+                deadline: Duration::from_millis(300),
+            },
+        );
+        assert!(start.elapsed() < Duration::from_millis(1500));
+
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0].provider, "slow");
+        assert_eq!(
+            outcomes[0].facts,
+            Err("deadline of 300ms exceeded".to_string())
+        );
+        assert_eq!(outcomes[1].facts, Ok(vec!["a fact from fast".into()]));
+        assert_eq!(outcomes[2].facts, Err("an error occurred".to_string()));
+    }
+
+    #[test]
+    fn test_run_counts_the_crawlers_past_the_deadline() {
+        let mut services = get_services(&["slow"], 1000);
+        services.append(&mut get_services(&["fast"], 10));
+
+        // The slow crawler keeps the only slot until it ends
+        let start = Instant::now();
+        let outcomes = run(
+            &services,
+            get_client(),
+            &Harvest {
+                concurrency: 1,
+                deadline: Duration::from_millis(300),
+            },
+        );
+        assert!(start.elapsed() >= Duration::from_millis(600));
+        assert_eq!(
+            outcomes[1].facts,
+            Err(
+                "not started, the providers past the deadline of 300ms are still running"
+                    .to_string()
+            )
+        );
+
+        let outcomes = run(
+            &services,
+            get_client(),
+            &Harvest {
+                concurrency: 1,
+                deadline: Duration::from_millis(800),
+            },
+        );
+        assert_eq!(outcomes[1].facts, Ok(vec!["a fact from fast".into()]));
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::ProviderHealth;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Health {
+    pub warn_after_failures: Option<usize>,
+}
+
+impl Health {
+    pub fn is_default(&self) -> bool {
+        *self == Health::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        matches!(self.warn_after_failures, Some(count) if count > 0)
+    }
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "warn_after_failures: {}",
+            self.warn_after_failures
+                .map(|c| c.to_string())
+                .unwrap_or("none".to_string())
+        )
+    }
+}
+
+// Return a warning for every enabled provider which failed too many times
+// in a row.
+pub fn get_warnings(
+    settings: &Health,
+    healths: &[ProviderHealth],
+    providers: &[String],
+) -> Vec<String> {
+    match settings.warn_after_failures {
+        Some(count) if count > 0 => healths
+            .iter()
+            .filter(|h| providers.contains(&h.provider) && h.consecutive_failures >= count)
+            .map(|h| {
+                format!(
+                    "the provider {} failed {} times in a row, run `cultura doctor providers-health` for more details",
+                    h.provider, h.consecutive_failures
+                )
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_health(provider: &str, consecutive_failures: usize) -> ProviderHealth {
+        ProviderHealth {
+            provider: provider.to_string(),
+            attempts: 10,
+            successes: 10 - consecutive_failures,
+            consecutive_failures,
+            last_attempt_at: "2023-05-01 10:00:00".to_string(),
+            last_duration_ms: 100,
+            last_found: 0,
+            last_inserted: 0,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_get_warnings() {
+        let healths = vec![
+            get_health("TIL", 3),
+            get_health("DYK", 2),
+            get_health("OLD", 5),
+        ];
+        let providers = vec!["TIL".to_string(), "DYK".to_string()];
+
+        assert!(get_warnings(&Health::default(), &healths, &providers).is_empty());
+        assert_eq!(
+            get_warnings(
+                &Health {
+                    warn_after_failures: Some(3)
+                },
+                &healths,
+                &providers
+            ),
+            vec!["the provider TIL failed 3 times in a row, run `cultura doctor providers-health` for more details"]
+        );
+        assert_eq!(
+            get_warnings(
+                &Health {
+                    warn_after_failures: Some(2)
+                },
+                &healths,
+                &providers
+            )
+            .len(),
+            2
+        );
+    }
+}
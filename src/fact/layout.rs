@@ -0,0 +1,306 @@
+use std::{
+    env,
+    fmt::{self, Display},
+    io,
+};
+
+use serde::{Deserialize, Serialize};
+use terminal_size::{terminal_size_of, Width};
+use unicode_width::UnicodeWidthChar;
+
+use super::{style::Style, template::Span};
+
+const ELLIPSIS: char = '…';
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    // The lines are wrapped to the terminal width, or to this width when the
+    // terminal is wider or unknown
+    pub max_width: Option<usize>,
+    // The number of lines a line of the template can be wrapped on
+    pub max_lines: Option<usize>,
+}
+
+impl Layout {
+    pub fn is_default(&self) -> bool {
+        *self == Layout::default()
+    }
+
+    // The width to wrap to, none when the output is not a terminal and no
+    // width is configured
+    pub fn get_width(&self) -> Option<usize> {
+        match (get_terminal_width(), self.max_width) {
+            (Some(terminal), Some(max)) => Some(terminal.min(max)),
+            (terminal, max) => terminal.or(max),
+        }
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to_string = |v: Option<usize>| v.map(|v| v.to_string()).unwrap_or("none".to_string());
+        write!(
+            f,
+            "max_width: {}, max_lines: {}",
+            to_string(self.max_width),
+            to_string(self.max_lines)
+        )
+    }
+}
+
+// The width of the terminal of the standard output, or the COLUMNS variable
+fn get_terminal_width() -> Option<usize> {
+    match terminal_size_of(io::stdout()) {
+        Some((Width(width), _)) if width > 0 => Some(width as usize),
+        _ => env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .filter(|c| *c > 0),
+    }
+}
+
+// A character of a rendered line with the index of its span
+type Cell = (char, usize);
+
+// Wrap every line of a rendered template between words, the wrapped parts of
+// a line are indented to start under the fact. A line wrapped on more than
+// max_lines lines is cut and ends with an ellipsis.
+pub fn wrap(spans: &[Span], width: Option<usize>, max_lines: Option<usize>) -> Vec<Span> {
+    let cells = spans
+        .iter()
+        .enumerate()
+        .flat_map(|(i, span)| span.text.chars().map(move |c| (c, i)))
+        .collect::<Vec<Cell>>();
+
+    let mut lines = vec![];
+    for line in cells.split(|(c, _)| *c == '\n') {
+        let mut wrapped = match width {
+            Some(width) => wrap_line(line, spans, width),
+            None => vec![line.to_vec()],
+        };
+        if let Some(max_lines) = max_lines.filter(|m| wrapped.len() > *m) {
+            wrapped.truncate(max_lines.max(1));
+            if let Some(last) = wrapped.last_mut() {
+                add_ellipsis(last, width);
+            }
+        }
+        lines.extend(wrapped);
+    }
+
+    // The spans are rebuilt from the cells, an indent has no span
+    let mut wrapped_spans: Vec<Span> = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            wrapped_spans.push(Span::new("\n", &[], false));
+        }
+        for (c, index) in line {
+            let (styles, fact) = match spans.get(*index) {
+                Some(span) => (span.styles.as_slice(), span.fact),
+                None => (&[] as &[Style], false),
+            };
+            match wrapped_spans.last_mut() {
+                Some(last) if last.styles == styles && last.fact == fact && last.text != "\n" => {
+                    last.text.push(*c)
+                }
+                _ => wrapped_spans.push(Span::new(&c.to_string(), styles, fact)),
+            }
+        }
+    }
+    wrapped_spans
+}
+
+fn wrap_line(line: &[Cell], spans: &[Span], width: usize) -> Vec<Vec<Cell>> {
+    // An indent taking more than half of the width leaves too little room
+    let indent = line
+        .iter()
+        .position(|(_, i)| spans[*i].fact)
+        .map(|p| get_width(&line[..p]))
+        .filter(|indent| *indent <= width / 2)
+        .unwrap_or(0);
+
+    let mut lines = vec![];
+    let mut current: Vec<Cell> = vec![];
+    let mut current_width = 0;
+    for (word, spaces) in split_words(line) {
+        let word_width = get_width(word);
+        let spaces_width = get_width(spaces);
+        if current_width + spaces_width + word_width <= width || current_width <= indent {
+            current.extend_from_slice(spaces);
+            current_width += spaces_width;
+        } else {
+            lines.push(std::mem::take(&mut current));
+            current = vec![(' ', usize::MAX); indent];
+            current_width = indent;
+        }
+        // A word longer than a line is cut
+        for cell in word {
+            let cell_width = get_width(&[*cell]);
+            if current_width + cell_width > width && current_width > indent {
+                lines.push(std::mem::take(&mut current));
+                current = vec![(' ', usize::MAX); indent];
+                current_width = indent;
+            }
+            current.push(*cell);
+            current_width += cell_width;
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+// Every word with the spaces preceding it
+fn split_words(line: &[Cell]) -> Vec<(&[Cell], &[Cell])> {
+    let mut words = vec![];
+    let mut start = 0;
+    while start < line.len() {
+        let word_start = (start..line.len())
+            .find(|i| line[*i].0 != ' ')
+            .unwrap_or(line.len());
+        let word_end = (word_start..line.len())
+            .find(|i| line[*i].0 == ' ')
+            .unwrap_or(line.len());
+        words.push((&line[word_start..word_end], &line[start..word_start]));
+        start = word_end;
+    }
+    words
+}
+
+fn add_ellipsis(line: &mut Vec<Cell>, width: Option<usize>) {
+    while line.last().is_some_and(|(c, _)| *c == ' ')
+        || width.is_some_and(|w| !line.is_empty() && get_width(line) + 1 > w)
+    {
+        line.pop();
+    }
+    let index = line.last().map(|(_, i)| *i).unwrap_or(usize::MAX);
+    line.push((ELLIPSIS, index));
+}
+
+fn get_width(cells: &[Cell]) -> usize {
+    cells.iter().map(|(c, _)| c.width().unwrap_or(0)).sum()
+}
+
+pub fn get_text_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+pub fn get_spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(|s| get_text_width(&s.text)).sum()
+}
+
+// The spans of every line of a wrapped text, without the line breaks
+pub fn split_lines(spans: &[Span]) -> Vec<Vec<Span>> {
+    let mut lines = vec![vec![]];
+    for span in spans {
+        for (i, text) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(vec![]);
+            }
+            if !text.is_empty() {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push(Span::new(text, &span.styles, span.fact));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fact::{
+        style::{Color, ColorDepth},
+        template::{self, Template, Values},
+    };
+
+    fn render(
+        template: &str,
+        fact: &str,
+        width: Option<usize>,
+        max_lines: Option<usize>,
+    ) -> String {
+        let spans = Template::parse(template)
+            .unwrap()
+            .render_spans(&Values::from([("fact", fact.to_string())]));
+        template::to_string(&wrap(&spans, width, max_lines), ColorDepth::Basic)
+    }
+
+    #[test]
+    fn test_wrap() {
+        let fact = "Did you know that sloths can hold their breath longer than dolphins?";
+        for (template, width, max_lines, expected) in [
+            ("$fact", None, None, fact),
+            ("$fact", Some(80), None, fact),
+            (
+                "|> $fact",
+                Some(24),
+                None,
+                "|> Did you know that\n   sloths can hold their\n   breath longer than\n   dolphins?",
+            ),
+            (
+                "\n|> $fact\n",
+                Some(25),
+                Some(2),
+                "\n|> Did you know that\n   sloths can hold their…\n",
+            ),
+            (
+                "|> $fact",
+                Some(21),
+                Some(2),
+                "|> Did you know that\n   sloths can hold…",
+            ),
+            ("$fact", None, Some(1), fact),
+            (
+                "A long prefix: $fact",
+                Some(20),
+                Some(2),
+                "A long prefix: Did\nyou know that sloth…",
+            ),
+            ("|> $fact", Some(8), Some(3), "|> Did\n   you\n   know…"),
+            ("|> $fact", Some(6), Some(2), "|> Did\n   yo…"),
+        ] {
+            assert_eq!(
+                render(template, fact, width, max_lines),
+                expected,
+                "{} {:?} {:?}",
+                template,
+                width,
+                max_lines
+            );
+        }
+
+        assert_eq!(
+            render(
+                "|> $fact",
+                "Pneumonoultramicroscopicsilicovolcanoconiosis",
+                Some(20),
+                None
+            ),
+            "|> Pneumonoultramicr\n   oscopicsilicovolc\n   anoconiosis"
+        );
+        assert_eq!(
+            render("$fact", "日本語のテキスト", Some(6), None),
+            "日本語\nのテキ\nスト"
+        );
+    }
+
+    #[test]
+    fn test_wrap_keeps_the_styles() {
+        let spans = vec![
+            Span::new("|> ", &[Style::Foreground(Color::Basic(6))], false),
+            Span::new("one two", &[Style::Bold], true),
+        ];
+        assert_eq!(
+            wrap(&spans, Some(8), None),
+            vec![
+                Span::new("|> ", &[Style::Foreground(Color::Basic(6))], false),
+                Span::new("one", &[Style::Bold], true),
+                Span::new("\n", &[], false),
+                Span::new("   ", &[], false),
+                Span::new("two", &[Style::Bold], true),
+            ]
+        );
+    }
+}
@@ -1,135 +1,379 @@
-use std::error::Error;
-
+use std::{
+    cell::OnceCell,
+    error::Error,
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Local};
 use colored::Colorize;
-use regex::Regex;
-
-use crate::{config::ConfigResolver, db, third_part::Crawler};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ConfigResolver,
+    db,
+    third_part::{http, Crawler, Item},
+};
+
+pub mod duplicate;
+pub mod filter;
+pub mod harvest;
+pub mod health;
+pub mod layout;
+pub mod normaliser;
+pub mod style;
+pub mod template;
+pub mod theme;
 
 const NO_FACT_MESSAGES: &str = "Stay tuned for more fascinating facts soon";
 
+// How the next fact to display is picked among the unread ones
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    #[default]
+    Newest,
+    Random,
+}
+
+impl SelectionStrategy {
+    pub fn is_default(&self) -> bool {
+        *self == SelectionStrategy::default()
+    }
+}
+
+impl Display for SelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionStrategy::Newest => write!(f, "newest"),
+            SelectionStrategy::Random => write!(f, "random"),
+        }
+    }
+}
+
+pub struct HarvestStats {
+    pub provider: String,
+    pub found: usize,
+    pub rejected: usize,
+    pub duplicates: usize,
+    pub inserted: usize,
+    pub error: Option<String>,
+}
+
+impl Display for HarvestStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(e) => write!(f, "{}: failed, {}", self.provider, e),
+            None => write!(
+                f,
+                "{}: {} found, {} rejected, {} duplicates, {} inserted",
+                self.provider, self.found, self.rejected, self.duplicates, self.inserted
+            ),
+        }
+    }
+}
+
 pub struct Fact<'a> {
     config_resolver: &'a ConfigResolver,
     fact: &'a db::Fact,
+    client: Arc<http::Client>,
     third_part_services: Vec<Box<dyn Crawler>>,
+    // Compiled the first time a fact is rendered
+    template: OnceCell<template::Template>,
 }
 
 impl<'a> Fact<'a> {
     pub fn new(
         config_resolver: &'a ConfigResolver,
         fact: &'a db::Fact,
+        client: Arc<http::Client>,
         third_part_services: Vec<Box<dyn Crawler>>,
     ) -> Self {
         Fact {
             config_resolver,
             fact,
+            client,
             third_part_services,
+            template: OnceCell::new(),
         }
     }
 
-    pub fn print_random(&self) -> Result<(), Box<dyn Error>> {
-        println!("{}", self.generate_output(self.generate_random()?));
+    pub fn print_random(&self, depth: style::ColorDepth) -> Result<(), Box<dyn Error>> {
+        println!("{}", self.generate_output(&self.generate_random()?, depth)?);
+        for warning in self.get_warnings()? {
+            println!("{}", format!("warning: {}", warning).dimmed());
+        }
         Ok(())
     }
 
-    pub fn update(&self) -> Result<(), Box<dyn Error>> {
-        let r: Result<(), Box<dyn Error>> = Ok(());
+    pub fn update(&self) -> Result<Vec<HarvestStats>, Box<dyn Error>> {
+        let filter = filter::Filter::new(&self.config_resolver.get_filter_rules())?;
+        // The stored facts are indexed once for the whole harvest
+        let deduplication = self.config_resolver.get_deduplication();
+        let mut deduplicator = duplicate::Deduplicator::new(
+            &deduplication,
+            if deduplication.enabled {
+                self.fact.get_facts()?
+            } else {
+                vec![]
+            },
+        );
 
-        self.third_part_services
-            .iter()
-            .map(|service: &Box<dyn Crawler>| -> Result<(), Box<dyn Error>> {
-                let parens = Regex::new("\\(.+\\)").unwrap();
-                let multi_space = Regex::new(r"\s+").unwrap();
-                let facts = service
-                    .get_facts()?
-                    .iter()
-                    .map(|s| parens.replace_all(s, "").to_string())
-                    .map(|s| multi_space.replace_all(s.as_str(), " ").to_string())
-                    .collect::<Vec<String>>();
-
-                let r: Result<(), Box<dyn Error>> = Ok(());
-
-                self.fact
-                    .create(service.get_id(), facts)
-                    .iter()
-                    .fold(r, |acc, item| match item {
-                        Ok(_) => acc,
-                        Err(e) => match acc {
-                            Ok(_) => Err(e.to_string().into()),
-                            Err(e_acc) => Err(format!("{}, {}", e_acc, e).into()),
-                        },
+        let stats = harvest::run(
+            &self.third_part_services,
+            self.client.clone(),
+            &self.config_resolver.get_harvest(),
+        )
+        .into_iter()
+        .zip(&self.third_part_services)
+        .map(
+            |(outcome, service)| -> Result<HarvestStats, Box<dyn Error>> {
+                let mut stats = HarvestStats {
+                    provider: outcome.provider.to_owned(),
+                    found: 0,
+                    rejected: 0,
+                    duplicates: 0,
+                    inserted: 0,
+                    error: None,
+                };
+                if let Err(e) = outcome
+                    .facts
+                    .map_err(|e| e.into())
+                    .and_then(|facts| self.store(&mut stats, facts, &filter, &mut deduplicator))
+                    // The page is cached once its facts are stored
+                    .and_then(|_| match service.get_url() {
+                        Some(url) => self.client.save(&url),
+                        None => Ok(()),
                     })
+                {
+                    stats.error = Some(e.to_string());
+                }
+
+                self.fact.create_harvest(
+                    stats.provider.to_owned(),
+                    outcome.started_at.to_string(),
+                    outcome.duration.as_millis() as u64,
+                    stats.found,
+                    stats.inserted,
+                    stats.error.to_owned(),
+                )?;
+                Ok(stats)
+            },
+        )
+        .collect::<Result<Vec<HarvestStats>, Box<dyn Error>>>();
+        // The pages of the failed providers are downloaded again next time
+        self.client.discard_fetched()?;
+        stats
+    }
+
+    fn store(
+        &self,
+        stats: &mut HarvestStats,
+        found_facts: Vec<Item>,
+        filter: &filter::Filter,
+        deduplicator: &mut duplicate::Deduplicator,
+    ) -> Result<(), Box<dyn Error>> {
+        let provider = stats.provider.to_owned();
+        let normaliser =
+            normaliser::Normaliser::new(&self.config_resolver.get_normalisation_steps(&provider))?;
+        let candidates = found_facts
+            .iter()
+            .map(|i| Item {
+                fact: normaliser.normalise(&i.fact),
+                ..i.clone()
             })
-            .fold(r, |acc, item| match item {
-                Ok(_) => acc,
-                Err(e) => match acc {
-                    Ok(_) => Err(e.to_string().into()),
-                    Err(e_acc) => Err(format!("{}, {}", e_acc, e).into()),
-                },
-            })
+            .filter(|i| filter.check(&provider, &i.fact).is_ok())
+            .collect::<Vec<Item>>();
+        stats.found = found_facts.len();
+        stats.rejected = found_facts.len() - candidates.len();
+
+        let r: Result<usize, Box<dyn Error>> = Ok(0);
+        let mut results = vec![];
+        for candidate in candidates {
+            match deduplicator.find(&candidate.fact) {
+                Some(m) => {
+                    stats.duplicates += self.fact.create_duplicate(
+                        m.id,
+                        provider.to_owned(),
+                        candidate.fact,
+                        m.similarity,
+                    )?;
+                }
+                None => {
+                    let fact = candidate.fact.to_owned();
+                    for result in self.fact.create(provider.to_owned(), vec![candidate]) {
+                        // The next facts of the batch are compared with this one
+                        if let Ok(Some(id)) = &result {
+                            deduplicator.add(id.to_owned(), fact.to_owned());
+                        }
+                        results.push(result);
+                    }
+                }
+            }
+        }
+
+        stats.inserted = results.iter().fold(r, |acc, item| match item {
+            Ok(Some(_)) => acc.map(|c| c + 1),
+            Ok(None) => acc,
+            Err(e) => match acc {
+                Ok(_) => Err(e.to_string().into()),
+                Err(e_acc) => Err(format!("{}, {}", e_acc, e).into()),
+            },
+        })?;
+        Ok(())
     }
 
-    fn generate_random(&self) -> Result<String, Box<dyn Error>> {
-        let data = self.fact.get_random_fact()?;
+    pub fn get_warnings(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let settings = self.config_resolver.get_health();
+        // The harvests are not queried when the warnings are disabled
+        if !settings.is_enabled() {
+            return Ok(vec![]);
+        }
+        Ok(health::get_warnings(
+            &settings,
+            &self.fact.get_providers_health()?,
+            &self
+                .third_part_services
+                .iter()
+                .map(|s| s.get_id())
+                .collect::<Vec<String>>(),
+        ))
+    }
 
-        Ok(match data {
-            Some((id, fact)) => {
-                self.fact.mark_as_read(id)?;
-                fact
+    // Pick the next fact, mark it as read and return the values of the
+    // template variables
+    fn generate_random(&self) -> Result<template::Values, Box<dyn Error>> {
+        let providers = self
+            .third_part_services
+            .iter()
+            .map(|s| s.get_id())
+            .collect::<Vec<String>>();
+        let data = self
+            .fact
+            .get_random_fact(&providers, &self.config_resolver.get_selection_strategy())?;
+
+        let mut values = template::Values::new();
+        match data {
+            Some(record) => {
+                self.fact.mark_as_read(record.id.clone())?;
+                values.insert(
+                    "date",
+                    DateTime::parse_from_str(&record.created_at, "%Y-%m-%d %H:%M:%S%.f %:z")
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default(),
+                );
+                values.insert("fact", record.fact);
+                values.insert("provider", record.provider);
+                values.insert("source_url", record.source_url.unwrap_or_default());
+                values.insert("title", record.title.unwrap_or_default());
+                values.insert("id", record.id);
             }
-            None => NO_FACT_MESSAGES.to_string(),
-        })
+            None => {
+                values.insert("fact", NO_FACT_MESSAGES.to_string());
+            }
+        }
+        // Counting the facts left is a query run only when the template shows it
+        if self.get_template()?.has_variable("remaining") {
+            values.insert(
+                "remaining",
+                self.fact.count_unread_facts(&providers)?.to_string(),
+            );
+        }
+        Ok(values)
     }
 
-    fn generate_output(&self, fact: String) -> String {
-        let mut template = self.config_resolver.get_template();
-        let t = template.clone();
+    fn get_template(&self) -> Result<&template::Template, Box<dyn Error>> {
+        if self.template.get().is_none() {
+            let _ = self.template.set(template::Template::parse(
+                &self.config_resolver.get_template(),
+            )?);
+        }
+        Ok(self.template.get().unwrap())
+    }
 
-        let data = Regex::new(r"(?:__(?:.+?)__|\$fact)(?::[a-z]+)*")
-            .unwrap()
-            .find_iter(t.as_str())
-            .map(|s| s.as_str())
-            .collect::<Vec<&str>>();
+    fn generate_output(
+        &self,
+        values: &template::Values,
+        depth: style::ColorDepth,
+    ) -> Result<String, Box<dyn Error>> {
+        let layout = self.config_resolver.get_layout();
+        let spans = self.config_resolver.get_theme().render(
+            self.get_template()?,
+            values,
+            layout.get_width(),
+            layout.max_lines,
+        );
+        Ok(template::to_string(&spans, depth))
+    }
+}
 
-        for d in data {
-            let items = d.split(":").collect::<Vec<&str>>();
-            let mut text_formatted = items
-                .get(0)
-                .unwrap()
-                .replace("__", "")
-                .replace("$fact", fact.as_str())
-                .to_string()
-                .normal();
-
-            for text_format in items.into_iter().skip(1) {
-                text_formatted = match text_format {
-                    "blue" => text_formatted.blue(),
-                    "red" => text_formatted.red(),
-                    "green" => text_formatted.green(),
-                    "black" => text_formatted.black(),
-                    "yellow" => text_formatted.yellow(),
-                    "white" => text_formatted.white(),
-                    "purple" => text_formatted.purple(),
-                    "cyan" => text_formatted.cyan(),
-                    "magenta" => text_formatted.magenta(),
-                    "bold" => text_formatted.bold(),
-                    "dimmed" => text_formatted.dimmed(),
-                    "italic" => text_formatted.italic(),
-                    "underline" => text_formatted.underline(),
-                    _ => text_formatted.normal(),
-                };
-            }
-            template = template.replace(d, format!("{}", text_formatted).as_str());
-        }
-        template
+pub fn check_template(template: &str) -> Result<(), Box<dyn Error>> {
+    if !template::Template::parse(template)?.has_variable("fact") {
+        Err("the $fact variable is missing")?
     }
+    Ok(())
+}
+
+// Render a template with a sample fact having every variable defined
+pub fn preview_template(
+    template: &str,
+    depth: style::ColorDepth,
+) -> Result<String, Box<dyn Error>> {
+    check_template(template)?;
+    Ok(template::Template::parse(template)?.render(&get_sample_values(), depth))
+}
+
+// Render the sample fact with every theme, the template theme uses the given
+// template
+pub fn preview_themes(
+    template: &str,
+    layout: &layout::Layout,
+    depth: style::ColorDepth,
+) -> Result<Vec<(theme::Theme, String)>, Box<dyn Error>> {
+    let template = template::Template::parse(template)?;
+    Ok(theme::THEMES
+        .iter()
+        .map(|theme| {
+            let spans = theme.render(
+                &template,
+                &get_sample_values(),
+                layout.get_width(),
+                layout.max_lines,
+            );
+            (*theme, template::to_string(&spans, depth))
+        })
+        .collect())
+}
+
+fn get_sample_values() -> template::Values {
+    template::Values::from([
+        (
+            "fact",
+            "Today I learned that koalas have fingerprints".to_string(),
+        ),
+        ("provider", "TIL".to_string()),
+        (
+            "source_url",
+            "https://www.reddit.com/r/todayilearned/".to_string(),
+        ),
+        ("date", Local::now().format("%Y-%m-%d").to_string()),
+        ("title", "Koala".to_string()),
+        ("remaining", "12".to_string()),
+        ("id", "0b5d3a4e-8d2c-4a57-9c1e-2f6b7e1d9a30".to_string()),
+    ])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Locations;
+    use crate::third_part::starter::Starter;
+    use chrono::Local;
     use rand::{distributions::Alphanumeric, Rng};
     use rusqlite::Connection;
     use serde::{Deserialize, Serialize};
+    use std::fs;
+    use style::ColorDepth;
     use tempfile::tempdir;
 
     #[derive(Serialize, Deserialize, Clone)]
@@ -139,13 +383,35 @@ mod tests {
 
     #[typetag::serde]
     impl Crawler for CrawlerMock {
-        fn get_facts(&self) -> Result<Vec<String>, Box<dyn Error>> {
-            Ok(self.facts.to_owned())
+        fn get_facts(&self, _: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+            Ok(self.facts.iter().map(|f| f.as_str().into()).collect())
         }
 
         fn get_id(&self) -> String {
             "crawlermock".to_string()
         }
+
+        fn get_description(&self) -> String {
+            "A mock".to_string()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    struct FailingCrawlerMock {}
+
+    #[typetag::serde]
+    impl Crawler for FailingCrawlerMock {
+        fn get_facts(&self, _: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+            Err("the page cannot be parsed".into())
+        }
+
+        fn get_id(&self) -> String {
+            "failingcrawlermock".to_string()
+        }
+
+        fn get_description(&self) -> String {
+            "A mock".to_string()
+        }
     }
 
     fn generate_random_string(prefix: &str, suffix: &str) -> String {
@@ -171,12 +437,21 @@ mod tests {
             "whatever 2".to_string(),
         ];
         let third_part_services: Vec<Box<dyn Crawler>> = vec![Box::new(CrawlerMock { facts })];
-        let config_resolver = ConfigResolver::new(Some(tempdir().unwrap().into_path())).unwrap();
-        let fact = Fact::new(&config_resolver, &f, third_part_services);
-        fact.update().unwrap();
+        let config_resolver =
+            ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+        let stats = fact.update().unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(
+            stats.first().unwrap().to_string(),
+            "crawlermock: 2 found, 0 rejected, 0 duplicates, 2 inserted"
+        );
 
         let conn = Connection::open(database_name).unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM facts").unwrap();
+        let mut stmt = conn
+            .prepare("SELECT * FROM facts WHERE provider = 'crawlermock'")
+            .unwrap();
         let mut rows = stmt.query([]).unwrap();
         let row1 = rows.next().unwrap().unwrap();
         assert_eq!("whatever 1", row1.get_unwrap::<usize, String>(1));
@@ -187,33 +462,327 @@ mod tests {
         assert_eq!("crawlermock", row2.get_unwrap::<usize, String>(2));
     }
 
+    #[test]
+    fn test_update_with_filters() {
+        let database_name = generate_random_string("update_with_filters", ".sqlite");
+
+        let f = crate::db::Fact::new(database_name.as_str()).unwrap();
+        let facts = vec![
+            "whatever 1".to_string(),
+            "whatever 2 (pictured)".to_string(),
+            "a blocked fact".to_string(),
+            "short".to_string(),
+        ];
+        let third_part_services: Vec<Box<dyn Crawler>> = vec![Box::new(CrawlerMock { facts })];
+        let path = tempdir().unwrap().into_path();
+        let config_resolver = ConfigResolver::new(Locations::new(&path)).unwrap();
+        std::fs::write(
+            config_resolver.get_config_file_path(),
+            r#"template = "$fact"
+providers = []
+
+[filters]
+blocklist = ["blocked"]
+min_length = 6
+"#,
+        )
+        .unwrap();
+        let config_resolver = ConfigResolver::new(Locations::new(&path)).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+
+        let stats = fact.update().unwrap();
+        assert_eq!(
+            stats.first().unwrap().to_string(),
+            "crawlermock: 4 found, 2 rejected, 0 duplicates, 2 inserted"
+        );
+
+        let stats = fact.update().unwrap();
+        assert_eq!(
+            stats.first().unwrap().to_string(),
+            "crawlermock: 4 found, 2 rejected, 0 duplicates, 0 inserted"
+        );
+    }
+
+    #[test]
+    fn test_update_with_near_duplicates() {
+        let database_name = generate_random_string("update_with_near_duplicates", ".sqlite");
+
+        let f = crate::db::Fact::new(database_name.as_str()).unwrap();
+        f.create(
+            "DYK".to_string(),
+            vec!["Did you know that sloths can hold their breath longer than dolphins?".into()],
+        );
+        let facts = vec![
+            "Today I learned that sloths can hold their breath longer than dolphins".to_string(),
+            "Today I learned that koalas have fingerprints".to_string(),
+            // A near-duplicate of a fact of the same harvest
+            "Did you know that koalas have fingerprints?".to_string(),
+        ];
+        let third_part_services: Vec<Box<dyn Crawler>> = vec![Box::new(CrawlerMock { facts })];
+        let config_resolver =
+            ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+
+        let stats = fact.update().unwrap();
+        assert_eq!(
+            stats.first().unwrap().to_string(),
+            "crawlermock: 3 found, 0 rejected, 2 duplicates, 1 inserted"
+        );
+
+        let duplicates = f.get_duplicates().unwrap();
+        assert_eq!(duplicates.len(), 2);
+        let d = duplicates
+            .iter()
+            .find(|d| d.original_provider == "DYK")
+            .unwrap();
+        assert_eq!(
+            d.fact,
+            "Today I learned that sloths can hold their breath longer than dolphins"
+        );
+        assert_eq!(d.provider, "crawlermock");
+        assert_eq!(
+            d.original_fact,
+            "Did you know that sloths can hold their breath longer than dolphins?"
+        );
+        assert_eq!(d.original_provider, "DYK");
+        assert_eq!(d.similarity, 1.0);
+        let d = duplicates
+            .iter()
+            .find(|d| d.original_provider == "crawlermock")
+            .unwrap();
+        assert_eq!(d.fact, "Did you know that koalas have fingerprints?");
+        assert_eq!(
+            d.original_fact,
+            "Today I learned that koalas have fingerprints"
+        );
+
+        let stats = fact.update().unwrap();
+        assert_eq!(
+            stats.first().unwrap().to_string(),
+            "crawlermock: 3 found, 0 rejected, 0 duplicates, 0 inserted"
+        );
+        assert_eq!(f.get_duplicates().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_update_records_the_providers_health() {
+        let database_name = generate_random_string("update_health", ".sqlite");
+
+        let f = crate::db::Fact::new(database_name.as_str()).unwrap();
+        let third_part_services: Vec<Box<dyn Crawler>> = vec![
+            Box::new(CrawlerMock {
+                facts: vec!["whatever 1".to_string()],
+            }),
+            Box::new(FailingCrawlerMock {}),
+        ];
+        let path = tempdir().unwrap().into_path();
+        let config_resolver = ConfigResolver::new(Locations::new(&path)).unwrap();
+        std::fs::write(
+            config_resolver.get_config_file_path(),
+            r#"template = "$fact"
+providers = []
+
+[health]
+warn_after_failures = 2
+"#,
+        )
+        .unwrap();
+        let config_resolver = ConfigResolver::new(Locations::new(&path)).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+
+        let stats = fact.update().unwrap();
+        assert_eq!(
+            stats.iter().map(|s| s.to_string()).collect::<Vec<String>>(),
+            vec![
+                "crawlermock: 1 found, 0 rejected, 0 duplicates, 1 inserted",
+                "failingcrawlermock: failed, the page cannot be parsed",
+            ]
+        );
+        assert!(fact.get_warnings().unwrap().is_empty());
+
+        fact.update().unwrap();
+        let healths = f.get_providers_health().unwrap();
+        assert_eq!(healths.len(), 2);
+
+        let h = &healths[0];
+        assert_eq!(h.provider, "crawlermock");
+        assert_eq!(h.attempts, 2);
+        assert_eq!(h.successes, 2);
+        assert_eq!(h.consecutive_failures, 0);
+        assert_eq!(h.last_found, 1);
+        assert_eq!(h.last_inserted, 0);
+        assert_eq!(h.last_error, None);
+
+        let h = &healths[1];
+        assert_eq!(h.provider, "failingcrawlermock");
+        assert_eq!(h.attempts, 2);
+        assert_eq!(h.successes, 0);
+        assert_eq!(h.consecutive_failures, 2);
+        assert_eq!(h.last_error, Some("the page cannot be parsed".to_string()));
+
+        assert_eq!(
+            fact.get_warnings().unwrap(),
+            vec!["the provider failingcrawlermock failed 2 times in a row, run `cultura doctor providers-health` for more details"]
+        );
+
+        // Only the last harvests are kept
+        for i in 0..crate::db::KEPT_HARVESTS {
+            f.create_harvest(
+                "crawlermock".to_string(),
+                format!("2100-01-01 00:00:00.{:03} +00:00", i),
+                1,
+                0,
+                0,
+                None,
+            )
+            .unwrap();
+        }
+        let healths = f.get_providers_health().unwrap();
+        assert_eq!(healths[0].attempts, crate::db::KEPT_HARVESTS);
+        assert_eq!(healths[1].attempts, 2);
+    }
+
     #[test]
     fn test_generate_random() {
         let database_name = &generate_random_string("generate_random", ".sqlite");
 
         let f = crate::db::Fact::new(database_name.as_str()).unwrap();
         f.create(
-            "til".to_string(),
-            vec!["fact1".to_string(), "fact2".to_string()],
+            "crawlermock".to_string(),
+            vec![
+                Item {
+                    fact: "fact1".to_string(),
+                    source_url: Some("https://example.com/fact1".to_string()),
+                    title: Some("Title".to_string()),
+                },
+                "fact2".into(),
+            ],
+        );
+        let third_part_services: Vec<Box<dyn Crawler>> =
+            vec![Box::new(CrawlerMock { facts: vec![] })];
+        let path = tempdir().unwrap().into_path();
+        let config_resolver = ConfigResolver::new(Locations::new(&path)).unwrap();
+        config_resolver
+            .set_template("$fact $remaining".to_string())
+            .unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+
+        let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+
+        let mut values = (1..=2)
+            .map(|_| fact.generate_random().unwrap())
+            .collect::<Vec<template::Values>>();
+        values.sort_by_key(|v| v["fact"].clone());
+        assert_eq!(values[0]["provider"], "crawlermock");
+        assert_eq!(values[0]["source_url"], "https://example.com/fact1");
+        assert_eq!(values[0]["title"], "Title");
+        assert_eq!(
+            values[0]["date"],
+            Local::now().format("%Y-%m-%d").to_string()
+        );
+        assert!(!values[0]["id"].is_empty());
+        assert_eq!(values[1]["fact"], "fact2");
+        assert_eq!(values[1]["source_url"], "");
+        assert_eq!(
+            values
+                .iter()
+                .map(|v| v["remaining"].as_str())
+                .collect::<Vec<&str>>(),
+            vec!["0", "1"]
         );
-        let third_part_services = vec![];
-        let config_resolver = ConfigResolver::new(Some(tempdir().unwrap().into_path())).unwrap();
 
-        let fact = Fact::new(&config_resolver, &f, third_part_services);
+        let v3 = fact.generate_random().unwrap();
+        assert_eq!(v3["fact"], NO_FACT_MESSAGES);
+        assert_eq!(v3["remaining"], "0");
+        assert!(!v3.contains_key("provider"));
+    }
 
-        let mut facts: Vec<String> = vec![];
+    #[test]
+    fn test_generate_random_with_the_random_strategy() {
+        let database_name = &generate_random_string("random_strategy", ".sqlite");
 
-        let f1 = fact.generate_random();
-        assert!(f1.is_ok());
-        facts.push(f1.ok().unwrap());
+        let f = crate::db::Fact::new(database_name.as_str()).unwrap();
+        f.create(
+            "crawlermock".to_string(),
+            (1..=10).map(|i| Item::from(format!("fact{}", i))).collect(),
+        );
+        let path = tempdir().unwrap().into_path();
+        let locations = Locations::new(&path);
+        fs::create_dir_all(&locations.config_dir).unwrap();
+        fs::write(
+            locations.config_dir.join("config.toml"),
+            "providers = []\ntemplate = \"$fact\"\nselection_strategy = \"random\"\n",
+        )
+        .unwrap();
+        let config_resolver = ConfigResolver::new(locations).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let fact = Fact::new(
+            &config_resolver,
+            &f,
+            client,
+            vec![Box::new(CrawlerMock { facts: vec![] })],
+        );
+
+        let mut facts = (1..=10)
+            .map(|_| fact.generate_random().unwrap()["fact"].clone())
+            .collect::<Vec<String>>();
+        facts.sort();
+        facts.dedup();
+        assert_eq!(facts.len(), 10);
+        assert_eq!(fact.generate_random().unwrap()["fact"], NO_FACT_MESSAGES);
+    }
+
+    #[test]
+    fn test_generate_random_from_starter_pack() {
+        let database_name = &generate_random_string("starter_pack", ".sqlite");
+
+        let f = crate::db::Fact::new(database_name.as_str()).unwrap();
+        let config_resolver =
+            ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+
+        {
+            let fact = Fact::new(&config_resolver, &f, client.clone(), vec![]);
+            assert_eq!(fact.generate_random().unwrap()["fact"], NO_FACT_MESSAGES);
+        }
+        {
+            let third_part_services: Vec<Box<dyn Crawler>> = vec![Box::new(Starter::new())];
+            let fact = Fact::new(&config_resolver, &f, client.clone(), third_part_services);
+            let data = fact.generate_random().unwrap();
+            assert!(Starter::new()
+                .get_facts(&client)
+                .unwrap()
+                .iter()
+                .any(|i| i.fact == data["fact"]));
+            assert_eq!(data["provider"], "STARTER");
+            // The default template doesn't show the facts left
+            assert!(!data.contains_key("remaining"));
+        }
+    }
 
-        let f2 = fact.generate_random();
-        assert!(f2.is_ok());
-        facts.push(f2.ok().unwrap());
+    #[test]
+    fn test_check_template() {
+        assert!(check_template("__|>__:cyan:bold $fact:yellow").is_ok());
+        match check_template("__|>__:cyan:blink $fact:yelow") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "unknown style blink at position 13"),
+        }
+        match check_template("__|>__:cyan") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(e.to_string(), "the $fact variable is missing"),
+        }
+    }
 
-        let f3 = fact.generate_random();
-        assert!(f3.is_ok());
-        assert!(f3.ok().unwrap() == NO_FACT_MESSAGES);
+    #[test]
+    fn test_preview_template() {
+        assert_eq!(
+            preview_template("$fact {[$provider]}{ ($title:bold)}", ColorDepth::Basic).unwrap(),
+            "Today I learned that koalas have fingerprints [TIL] (\u{1b}[1mKoala\u{1b}[0m)"
+        );
+        assert!(preview_template("__|>__:cyan", ColorDepth::Basic).is_err());
     }
 
     #[test]
@@ -221,11 +790,19 @@ mod tests {
         let database_name = &generate_random_string("generate_output", ".sqlite");
         let f = crate::db::Fact::new(database_name.as_str()).unwrap();
         let third_part_services = vec![];
-        let config_resolver = ConfigResolver::new(Some(tempdir().unwrap().into_path())).unwrap();
+        let config_resolver =
+            ConfigResolver::new(Locations::new(&tempdir().unwrap().into_path())).unwrap();
+        let client = Arc::new(http::Client::new(&http::Network::default(), None).unwrap());
+        let values = template::Values::from([("fact", "fact1".to_string())]);
 
         {
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
 
             assert_eq!(data, "\n\u{1b}[36m|>\u{1b}[0m \u{1b}[33mfact1\u{1b}[0m\n");
         }
@@ -238,8 +815,13 @@ __|>__:cyan $fact:yellow"#
                         .to_string(),
                 )
                 .unwrap();
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
 
             assert_eq!(
                 data,
@@ -251,33 +833,56 @@ __|>__:cyan $fact:yellow"#
             config_resolver
                 .set_template("$fact:red".to_string())
                 .unwrap();
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
             assert_eq!(data, "\u{1b}[31mfact1\u{1b}[0m");
         }
         {
             config_resolver
-                .set_template("__A text between space__:magenta:bold".to_string())
+                .set_template("__A text between space__:magenta:bold $fact".to_string())
                 .unwrap();
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
-            assert_eq!(data, "\u{1b}[1;35mA text between space\u{1b}[0m");
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
+            assert_eq!(data, "\u{1b}[1;35mA text between space\u{1b}[0m fact1");
         }
         {
             config_resolver
-                .set_template("__ATextWithoutStyle__ __ATextWithStyles__:magenta".to_string())
+                .set_template("__ATextWithoutStyle__ __ATextWithStyles__:magenta $fact".to_string())
                 .unwrap();
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
-            assert_eq!(data, "ATextWithoutStyle \u{1b}[35mATextWithStyles\u{1b}[0m");
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
+            assert_eq!(
+                data,
+                "ATextWithoutStyle \u{1b}[35mATextWithStyles\u{1b}[0m fact1"
+            );
         }
         {
             config_resolver
-                .set_template("__A_text_with_dashes__:magenta".to_string())
+                .set_template("__A_text_with_dashes__:magenta $fact".to_string())
                 .unwrap();
-            let fact = Fact::new(&config_resolver, &f, third_part_services.clone());
-            let data = fact.generate_output("fact1".to_string());
-            assert_eq!(data, "\u{1b}[35mA_text_with_dashes\u{1b}[0m");
+            let fact = Fact::new(
+                &config_resolver,
+                &f,
+                client.clone(),
+                third_part_services.clone(),
+            );
+            let data = fact.generate_output(&values, ColorDepth::Basic).unwrap();
+            assert_eq!(data, "\u{1b}[35mA_text_with_dashes\u{1b}[0m fact1");
         }
     }
 }
@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    RegexReplace {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+    StripParentheses,
+    CollapseWhitespace,
+    DecodeHtmlEntities,
+    RemoveCitationMarkers,
+    TrimTrailingQuestionMarks,
+    Capitalise,
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::RegexReplace {
+                pattern,
+                replacement,
+            } => write!(f, "regex_replace({} => {})", pattern, replacement),
+            Step::StripParentheses => write!(f, "strip_parentheses"),
+            Step::CollapseWhitespace => write!(f, "collapse_whitespace"),
+            Step::DecodeHtmlEntities => write!(f, "decode_html_entities"),
+            Step::RemoveCitationMarkers => write!(f, "remove_citation_markers"),
+            Step::TrimTrailingQuestionMarks => write!(f, "trim_trailing_question_marks"),
+            Step::Capitalise => write!(f, "capitalise"),
+        }
+    }
+}
+
+pub fn get_default_steps() -> Vec<Step> {
+    vec![Step::StripParentheses, Step::CollapseWhitespace]
+}
+
+enum CompiledStep {
+    Replace(Regex, String),
+    DecodeHtmlEntities,
+    TrimTrailingQuestionMarks,
+    Capitalise,
+}
+
+pub struct Normaliser {
+    steps: Vec<CompiledStep>,
+}
+
+impl Normaliser {
+    pub fn new(steps: &[Step]) -> Result<Normaliser, Box<dyn Error>> {
+        Ok(Normaliser {
+            steps: steps
+                .iter()
+                .map(|step| -> Result<CompiledStep, Box<dyn Error>> {
+                    Ok(match step {
+                        Step::RegexReplace {
+                            pattern,
+                            replacement,
+                        } => CompiledStep::Replace(Regex::new(pattern)?, replacement.to_owned()),
+                        Step::StripParentheses => {
+                            CompiledStep::Replace(Regex::new(r"\([^()]*\)")?, String::new())
+                        }
+                        Step::CollapseWhitespace => {
+                            CompiledStep::Replace(Regex::new(r"\s+")?, " ".to_string())
+                        }
+                        Step::DecodeHtmlEntities => CompiledStep::DecodeHtmlEntities,
+                        Step::RemoveCitationMarkers => CompiledStep::Replace(
+                            Regex::new(r"\[(?:\d+|[a-z]|citation needed)\]")?,
+                            String::new(),
+                        ),
+                        Step::TrimTrailingQuestionMarks => CompiledStep::TrimTrailingQuestionMarks,
+                        Step::Capitalise => CompiledStep::Capitalise,
+                    })
+                })
+                .collect::<Result<Vec<CompiledStep>, Box<dyn Error>>>()?,
+        })
+    }
+
+    pub fn normalise(&self, fact: &str) -> String {
+        self.steps
+            .iter()
+            .fold(fact.to_string(), |acc, step| match step {
+                CompiledStep::Replace(regex, replacement) => {
+                    regex.replace_all(&acc, replacement.as_str()).to_string()
+                }
+                CompiledStep::DecodeHtmlEntities => {
+                    html_escape::decode_html_entities(&acc).to_string()
+                }
+                CompiledStep::TrimTrailingQuestionMarks => {
+                    acc.trim_end().trim_end_matches('?').to_string()
+                }
+                CompiledStep::Capitalise => {
+                    let mut chars = acc.chars();
+                    match chars.next() {
+                        Some(c) => c.to_uppercase().chain(chars).collect(),
+                        None => acc,
+                    }
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalise() {
+        let scenarios = vec![
+            (
+                get_default_steps(),
+                "whatever (whatever whatever)  1 (pictured)",
+                "whatever 1 ",
+            ),
+            (
+                vec![Step::DecodeHtmlEntities],
+                "Tom &amp; Jerry&#39;s &quot;house&quot;",
+                r#"Tom & Jerry's "house""#,
+            ),
+            (
+                vec![Step::RemoveCitationMarkers],
+                "a fact[1] with markers[a][citation needed]",
+                "a fact with markers",
+            ),
+            (
+                vec![Step::TrimTrailingQuestionMarks, Step::Capitalise],
+                "éclair is a pastry?? ",
+                "Éclair is a pastry",
+            ),
+            (
+                vec![
+                    Step::RegexReplace {
+                        pattern: r"^Did you know that ".to_string(),
+                        replacement: String::new(),
+                    },
+                    Step::Capitalise,
+                ],
+                "Did you know that cats sleep a lot?",
+                "Cats sleep a lot?",
+            ),
+            (vec![], "(born 1912)", "(born 1912)"),
+        ];
+
+        for (steps, input, expected) in scenarios {
+            assert_eq!(
+                Normaliser::new(&steps).unwrap().normalise(input),
+                expected,
+                "steps = {:?}",
+                steps
+            );
+        }
+    }
+
+    #[test]
+    fn test_normaliser_with_an_invalid_regex() {
+        match Normaliser::new(&[Step::RegexReplace {
+            pattern: "(".to_string(),
+            replacement: String::new(),
+        }]) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert!(e.to_string().contains("regex parse error")),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    env,
+    error::Error,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+// The basic colours, their index is their ANSI code and `bright_` adds 8
+const COLORS: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+// The xterm values of the 16 basic colours used to downgrade the others
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// The levels of the 6x6x6 cube of the 256 colours
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    // One of the 16 basic colours
+    Basic(u8),
+    // One of the 256 colours
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn parse(name: &str) -> Option<Color> {
+        let name = if name == "purple" { "magenta" } else { name };
+        if let Some(i) = COLORS.iter().position(|c| *c == name) {
+            return Some(Color::Basic(i as u8));
+        }
+        if let Some(i) = name
+            .strip_prefix("bright_")
+            .and_then(|n| COLORS.iter().position(|c| *c == n))
+        {
+            return Some(Color::Basic(i as u8 + 8));
+        }
+        if let Some(index) = name.strip_prefix("color") {
+            if index.chars().all(|c| c.is_ascii_digit()) {
+                return index.parse().ok().map(Color::Indexed);
+            }
+        }
+        let hex = name.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Color::Rgb(component(0)?, component(2)?, component(4)?))
+    }
+
+    // The closest colour the terminal can display
+    fn downgrade(self, depth: ColorDepth) -> Color {
+        match (self, depth) {
+            (Color::Rgb(r, g, b), ColorDepth::Indexed) => Color::Indexed(to_indexed(r, g, b)),
+            (Color::Indexed(i), ColorDepth::Basic) if i < 16 => Color::Basic(i),
+            (Color::Rgb(..) | Color::Indexed(_), ColorDepth::Basic) => {
+                let (r, g, b) = self.to_rgb();
+                Color::Basic(closest(&PALETTE, (r, g, b)) as u8)
+            }
+            _ => self,
+        }
+    }
+
+    fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Basic(i) => PALETTE[i as usize],
+            Color::Indexed(i) if i < 16 => PALETTE[i as usize],
+            Color::Indexed(i) if i < 232 => {
+                let i = i - 16;
+                (
+                    CUBE[(i / 36) as usize],
+                    CUBE[(i / 6 % 6) as usize],
+                    CUBE[(i % 6) as usize],
+                )
+            }
+            Color::Indexed(i) => {
+                let level = 8 + (i - 232) * 10;
+                (level, level, level)
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    // The SGR parameters, `background` selects the background codes
+    fn to_code(self, background: bool) -> String {
+        let offset = if background { 10 } else { 0 };
+        match self {
+            Color::Basic(i) if i < 8 => format!("{}", 30 + offset + i),
+            Color::Basic(i) => format!("{}", 90 + offset + i - 8),
+            Color::Indexed(i) => format!("{};5;{}", 38 + offset, i),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", 38 + offset, r, g, b),
+        }
+    }
+}
+
+// The colours a terminal can display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    // The output is not coloured
+    None,
+    Basic,
+    Indexed,
+    TrueColor,
+}
+
+impl ColorDepth {
+    // Terminals announce the true colours with COLORTERM and the 256 colours
+    // with their TERM
+    pub fn detect() -> ColorDepth {
+        match env::var("COLORTERM").as_deref() {
+            Ok("truecolor") | Ok("24bit") => ColorDepth::TrueColor,
+            _ => match env::var("TERM") {
+                Ok(term) if term.contains("256color") => ColorDepth::Indexed,
+                _ => ColorDepth::Basic,
+            },
+        }
+    }
+}
+
+// Whether the output is colored, given with `--color`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn should_colorize(&self) -> bool {
+        self.resolve(|name| env::var(name).ok(), io::stdout().is_terminal())
+    }
+
+    pub fn get_color_depth(&self) -> ColorDepth {
+        match self.should_colorize() {
+            true => ColorDepth::detect(),
+            false => ColorDepth::None,
+        }
+    }
+
+    // CLICOLOR_FORCE wins over NO_COLOR like in the colored crate, a dumb
+    // terminal or an output which is not a terminal is not colored
+    fn resolve(&self, var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+                    return true;
+                }
+                if var("NO_COLOR").is_some_and(|v| !v.is_empty())
+                    || var("CLICOLOR").as_deref() == Some("0")
+                    || var("TERM").as_deref() == Some("dumb")
+                {
+                    return false;
+                }
+                is_terminal
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "auto" => ColorChoice::Auto,
+            "always" => ColorChoice::Always,
+            "never" => ColorChoice::Never,
+            _ => Err(format!(
+                "unknown color choice {}, expected auto, always or never",
+                s
+            ))?,
+        })
+    }
+}
+
+// A style given after a `:` to a text or a variable of a template
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Foreground(Color),
+    Background(Color),
+    Bold,
+    Dimmed,
+    Italic,
+    Underline,
+    Reversed,
+    Strikethrough,
+}
+
+// The attributes in the order they are written in a sequence, the one used
+// by the colored crate so the sequences are unchanged
+const ATTRIBUTES: &[(Style, &str)] = &[
+    (Style::Bold, "1"),
+    (Style::Dimmed, "2"),
+    (Style::Underline, "4"),
+    (Style::Reversed, "7"),
+    (Style::Italic, "3"),
+    (Style::Strikethrough, "9"),
+];
+
+impl Style {
+    pub fn parse(name: &str) -> Option<Style> {
+        Some(match name {
+            "bold" => Style::Bold,
+            "dimmed" => Style::Dimmed,
+            "italic" => Style::Italic,
+            "underline" => Style::Underline,
+            "reversed" => Style::Reversed,
+            "strikethrough" => Style::Strikethrough,
+            _ => match name.strip_prefix("bg_") {
+                Some(name) => Style::Background(Color::parse(name)?),
+                None => Style::Foreground(Color::parse(name)?),
+            },
+        })
+    }
+}
+
+// Wrap a text in the escape sequence of its styles, a later colour replaces
+// an earlier one
+pub fn apply(text: &str, styles: &[Style], depth: ColorDepth) -> String {
+    if styles.is_empty() || depth == ColorDepth::None {
+        return text.to_string();
+    }
+    let mut codes = ATTRIBUTES
+        .iter()
+        .filter(|(attribute, _)| styles.contains(attribute))
+        .map(|(_, code)| code.to_string())
+        .collect::<Vec<String>>();
+    if let Some(color) = styles.iter().rev().find_map(|s| match s {
+        Style::Background(color) => Some(color),
+        _ => None,
+    }) {
+        codes.push(color.downgrade(depth).to_code(true));
+    }
+    if let Some(color) = styles.iter().rev().find_map(|s| match s {
+        Style::Foreground(color) => Some(color),
+        _ => None,
+    }) {
+        codes.push(color.downgrade(depth).to_code(false));
+    }
+    format!("\u{1b}[{}m{}\u{1b}[0m", codes.join(";"), text)
+}
+
+fn to_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let cube_level = |v: u8| closest(&CUBE.map(|l| (l, l, l)), (v, v, v));
+    let (ri, gi, bi) = (cube_level(r), cube_level(g), cube_level(b));
+    let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let grey_index = (average.saturating_sub(3) / 10).min(23);
+    let grey = (8 + grey_index * 10) as u8;
+    if distance((grey, grey, grey), (r, g, b)) < distance(cube, (r, g, b)) {
+        232 + grey_index as u8
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+fn closest(colors: &[(u8, u8, u8)], color: (u8, u8, u8)) -> usize {
+    (0..colors.len())
+        .min_by_key(|i| distance(colors[*i], color))
+        .unwrap_or(0)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Style::parse("cyan"),
+            Some(Style::Foreground(Color::Basic(6)))
+        );
+        assert_eq!(Style::parse("purple"), Style::parse("magenta"));
+        assert_eq!(
+            Style::parse("bright_red"),
+            Some(Style::Foreground(Color::Basic(9)))
+        );
+        assert_eq!(
+            Style::parse("bg_bright_black"),
+            Some(Style::Background(Color::Basic(8)))
+        );
+        assert_eq!(
+            Style::parse("color208"),
+            Some(Style::Foreground(Color::Indexed(208)))
+        );
+        assert_eq!(
+            Style::parse("bg_#FF8800"),
+            Some(Style::Background(Color::Rgb(255, 136, 0)))
+        );
+        assert_eq!(Style::parse("strikethrough"), Some(Style::Strikethrough));
+        for name in [
+            "blink",
+            "bright_purple",
+            "color256",
+            "color",
+            "#ff88",
+            "#gg8800",
+            "bg_bold",
+        ] {
+            assert_eq!(Style::parse(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_color_choice() {
+        let resolve = |choice: &str, vars: &[(&str, &str)], is_terminal: bool| {
+            ColorChoice::from_str(choice).unwrap().resolve(
+                |name| {
+                    vars.iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|(_, v)| v.to_string())
+                },
+                is_terminal,
+            )
+        };
+        assert!(resolve("auto", &[], true));
+        assert!(!resolve("auto", &[], false));
+        assert!(!resolve("auto", &[("NO_COLOR", "1")], true));
+        assert!(resolve("auto", &[("NO_COLOR", "")], true));
+        assert!(!resolve("auto", &[("TERM", "dumb")], true));
+        assert!(!resolve("auto", &[("CLICOLOR", "0")], true));
+        assert!(resolve(
+            "auto",
+            &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")],
+            false
+        ));
+        assert!(!resolve("auto", &[("CLICOLOR_FORCE", "0")], false));
+        assert!(resolve("always", &[("NO_COLOR", "1")], false));
+        assert!(!resolve("never", &[("CLICOLOR_FORCE", "1")], true));
+        match ColorChoice::from_str("sometimes") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "unknown color choice sometimes, expected auto, always or never"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let parse = |names: &[&str]| -> Vec<Style> {
+            names.iter().map(|n| Style::parse(n).unwrap()).collect()
+        };
+        for (styles, depth, expected) in [
+            (vec![], ColorDepth::TrueColor, "text"),
+            (vec!["magenta", "bold"], ColorDepth::None, "text"),
+            (
+                vec!["magenta", "bold"],
+                ColorDepth::Basic,
+                "\u{1b}[1;35mtext\u{1b}[0m",
+            ),
+            (
+                vec!["red", "blue"],
+                ColorDepth::Basic,
+                "\u{1b}[34mtext\u{1b}[0m",
+            ),
+            (
+                vec![
+                    "bright_cyan",
+                    "bg_bright_black",
+                    "strikethrough",
+                    "reversed",
+                ],
+                ColorDepth::Basic,
+                "\u{1b}[7;9;100;96mtext\u{1b}[0m",
+            ),
+            (
+                vec!["color208"],
+                ColorDepth::Indexed,
+                "\u{1b}[38;5;208mtext\u{1b}[0m",
+            ),
+            (
+                vec!["color208"],
+                ColorDepth::Basic,
+                "\u{1b}[33mtext\u{1b}[0m",
+            ),
+            (vec!["color4"], ColorDepth::Basic, "\u{1b}[34mtext\u{1b}[0m"),
+            (
+                vec!["#ff8800", "bg_#202020"],
+                ColorDepth::TrueColor,
+                "\u{1b}[48;2;32;32;32;38;2;255;136;0mtext\u{1b}[0m",
+            ),
+            (
+                vec!["#ff8700", "bg_#202020"],
+                ColorDepth::Indexed,
+                "\u{1b}[48;5;234;38;5;208mtext\u{1b}[0m",
+            ),
+            (
+                vec!["#00ff00"],
+                ColorDepth::Basic,
+                "\u{1b}[92mtext\u{1b}[0m",
+            ),
+        ] {
+            assert_eq!(
+                apply("text", &parse(&styles), depth),
+                expected,
+                "{:?}",
+                styles
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::{collections::HashMap, error::Error};
+
+use super::style::{self, ColorDepth, Style};
+
+// The variables known by the templates
+pub const VARIABLES: &[&str] = &[
+    "fact",
+    "provider",
+    "source_url",
+    "date",
+    "title",
+    "remaining",
+    "id",
+];
+
+// The characters a `\` turns into text
+const ESCAPED: &[char] = &['\\', '_', '$', '{', '}', ':'];
+
+// The values of the variables, a missing variable is rendered as empty
+pub type Values = HashMap<&'static str, String>;
+
+#[derive(Debug, PartialEq)]
+pub enum Node {
+    Text(String),
+    // A text written between `__` followed by its styles
+    Styled {
+        text: String,
+        styles: Vec<Style>,
+    },
+    // A `$variable` followed by its styles
+    Variable {
+        name: &'static str,
+        styles: Vec<Style>,
+    },
+    // A part written between `{` and `}` which is only rendered when all its
+    // variables have a value
+    Section(Vec<Node>),
+}
+
+// A piece of a rendered template, `fact` is set for the value of `$fact`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub styles: Vec<Style>,
+    pub fact: bool,
+}
+
+impl Span {
+    pub fn new(text: &str, styles: &[Style], fact: bool) -> Span {
+        Span {
+            text: text.to_string(),
+            styles: styles.to_vec(),
+            fact,
+        }
+    }
+}
+
+// A template compiled once then rendered with the values of its variables,
+// the values are never parsed so a fact may contain `__` or `$fact`.
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template, Box<dyn Error>> {
+        let mut parser = Parser {
+            chars: template.chars().collect(),
+            position: 0,
+        };
+        let nodes = parser.parse_nodes(None)?;
+        Ok(Template { nodes })
+    }
+
+    pub fn render(&self, values: &Values, depth: ColorDepth) -> String {
+        to_string(&self.render_spans(values), depth)
+    }
+
+    pub fn render_spans(&self, values: &Values) -> Vec<Span> {
+        let mut spans = vec![];
+        render_nodes(&self.nodes, values, &mut spans);
+        spans
+    }
+
+    pub fn has_variable(&self, name: &str) -> bool {
+        get_variables(&self.nodes).contains(&name)
+    }
+}
+
+// The positions given in the errors are the 1-based indexes of the characters
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    // Parse until the end of the template, or until the `}` closing the
+    // section opened at the given position
+    fn parse_nodes(&mut self, section: Option<usize>) -> Result<Vec<Node>, Box<dyn Error>> {
+        let mut nodes = vec![];
+        let mut text = String::new();
+        loop {
+            let c = match self.chars.get(self.position) {
+                Some(c) => *c,
+                None => match section {
+                    Some(start) => Err(format!(
+                        "the section opened at position {} is not closed",
+                        start + 1
+                    ))?,
+                    None => break,
+                },
+            };
+            let node = match c {
+                '\\' => match self.chars.get(self.position + 1) {
+                    Some(escaped) if ESCAPED.contains(escaped) => {
+                        text.push(*escaped);
+                        self.position += 2;
+                        continue;
+                    }
+                    _ => None,
+                },
+                '{' => {
+                    let start = self.position;
+                    self.position += 1;
+                    Some(Node::Section(self.parse_nodes(Some(start))?))
+                }
+                '}' => match section {
+                    Some(_) => {
+                        self.position += 1;
+                        break;
+                    }
+                    None => Err(format!(
+                        "unexpected }} at position {}, write \\}} to display it",
+                        self.position + 1
+                    ))?,
+                },
+                '$' => self.parse_variable()?,
+                '_' => self.parse_styled()?,
+                _ => None,
+            };
+            match node {
+                Some(node) => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+                    nodes.push(node);
+                }
+                None => {
+                    text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        Ok(nodes)
+    }
+
+    // A `$` which is not followed by a name is kept as text, `$5` for instance
+    fn parse_variable(&mut self) -> Result<Option<Node>, Box<dyn Error>> {
+        let name = self.read_name(self.position + 1);
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        let name = match VARIABLES.iter().find(|v| **v == name) {
+            Some(name) => name,
+            None => Err(format!(
+                "unknown variable ${} at position {}, write \\$ to display a $",
+                name,
+                self.position + 1
+            ))?,
+        };
+        self.position += 1 + name.len();
+        Ok(Some(Node::Variable {
+            name,
+            styles: self.parse_styles()?,
+        }))
+    }
+
+    // A `__` which is not closed on the same line is kept as text
+    fn parse_styled(&mut self) -> Result<Option<Node>, Box<dyn Error>> {
+        if self.chars.get(self.position + 1) != Some(&'_') {
+            return Ok(None);
+        }
+        let start = self.position + 2;
+        let line = match self.chars[start..].split(|c| *c == '\n').next() {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        // The text is at least one character
+        let length = match (1..line.len().saturating_sub(1))
+            .find(|i| line[*i] == '_' && line[*i + 1] == '_')
+        {
+            Some(length) => length,
+            None => return Ok(None),
+        };
+        self.position = start + length + 2;
+        Ok(Some(Node::Styled {
+            text: line[..length].iter().collect(),
+            styles: self.parse_styles()?,
+        }))
+    }
+
+    fn parse_styles(&mut self) -> Result<Vec<Style>, Box<dyn Error>> {
+        let mut styles = vec![];
+        while self.chars.get(self.position) == Some(&':') {
+            let name = read_style_name(&self.chars[self.position + 1..]);
+            if name.is_empty() {
+                break;
+            }
+            match Style::parse(&name) {
+                Some(style) => styles.push(style),
+                None => Err(format!(
+                    "unknown style {} at position {}",
+                    name,
+                    self.position + 2
+                ))?,
+            }
+            self.position += 1 + name.len();
+        }
+        Ok(styles)
+    }
+
+    fn read_name(&self, start: usize) -> String {
+        self.chars[start.min(self.chars.len())..]
+            .iter()
+            .take_while(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || **c == '_')
+            .collect()
+    }
+}
+
+fn render_nodes(nodes: &[Node], values: &Values, spans: &mut Vec<Span>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => spans.push(Span::new(text, &[], false)),
+            Node::Styled { text, styles } => spans.push(Span::new(text, styles, false)),
+            Node::Variable { name, styles } => match values.get(name) {
+                Some(value) if !value.is_empty() => {
+                    spans.push(Span::new(value, styles, *name == "fact"))
+                }
+                _ => {}
+            },
+            // The variables of the nested sections only hide these sections
+            Node::Section(nodes) => {
+                if nodes.iter().all(|node| match node {
+                    Node::Variable { name, .. } => values.get(name).is_some_and(|v| !v.is_empty()),
+                    _ => true,
+                }) {
+                    render_nodes(nodes, values, spans)
+                }
+            }
+        }
+    }
+}
+
+pub fn to_string(spans: &[Span], depth: ColorDepth) -> String {
+    spans
+        .iter()
+        .map(|s| style::apply(&s.text, &s.styles, depth))
+        .collect()
+}
+
+fn get_variables(nodes: &[Node]) -> Vec<&'static str> {
+    let mut variables = vec![];
+    for node in nodes {
+        match node {
+            Node::Variable { name, .. } => variables.push(*name),
+            Node::Section(nodes) => variables.extend(get_variables(nodes)),
+            _ => {}
+        }
+    }
+    variables
+}
+
+// A style name is made of lowercase letters and underscores, with digits
+// after the color prefix and hexadecimal digits after a #, so a text written
+// right after a variable, `$fact:1` for instance, is not read as a style.
+fn read_style_name(chars: &[char]) -> String {
+    let mut name = chars
+        .iter()
+        .take_while(|c| c.is_ascii_lowercase() || **c == '_')
+        .collect::<String>();
+    let rest = &chars[name.len()..];
+    if name.ends_with("color") {
+        name.extend(rest.iter().take_while(|c| c.is_ascii_digit()));
+    } else if rest.first() == Some(&'#') && (name.is_empty() || name == "bg_") {
+        name.push('#');
+        name.extend(rest[1..].iter().take_while(|c| c.is_ascii_hexdigit()));
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fact::style::Color;
+
+    fn get_values() -> Values {
+        Values::from([
+            ("fact", "fact1".to_string()),
+            ("provider", "TIL".to_string()),
+            ("source_url", "".to_string()),
+        ])
+    }
+
+    fn get_error(template: &str) -> String {
+        match Template::parse(template) {
+            Ok(_) => panic!("must return an error: {}", template),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Template::parse(r"__|>__:cyan:bold $fact\s {($source_url)} $5 a__b \$fact\: \_\_")
+                .unwrap(),
+            Template {
+                nodes: vec![
+                    Node::Styled {
+                        text: "|>".to_string(),
+                        styles: vec![Style::Foreground(Color::Basic(6)), Style::Bold]
+                    },
+                    Node::Text(" ".to_string()),
+                    Node::Variable {
+                        name: "fact",
+                        styles: vec![]
+                    },
+                    Node::Text(r"\s ".to_string()),
+                    Node::Section(vec![
+                        Node::Text("(".to_string()),
+                        Node::Variable {
+                            name: "source_url",
+                            styles: vec![]
+                        },
+                        Node::Text(")".to_string()),
+                    ]),
+                    Node::Text(" $5 a__b $fact: __".to_string()),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            get_error("$fact {$title"),
+            "the section opened at position 7 is not closed"
+        );
+        assert_eq!(
+            get_error("$fact}"),
+            r"unexpected } at position 6, write \} to display it"
+        );
+        assert_eq!(
+            get_error("$facts"),
+            r"unknown variable $facts at position 1, write \$ to display a $"
+        );
+        assert_eq!(
+            get_error("__|>__:cyan $fact:bright_pink"),
+            "unknown style bright_pink at position 19"
+        );
+        assert_eq!(
+            get_error("$fact:bold:blink"),
+            "unknown style blink at position 12"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let values = get_values();
+        for (template, expected) in [
+            ("$fact {from $provider}", "fact1 from TIL"),
+            ("$fact{ ($source_url)}", "fact1"),
+            ("$fact{ ($title)}", "fact1"),
+            ("$fact{ {$provider}{$title}}", "fact1 TIL"),
+            ("$fact:red", "\u{1b}[31mfact1\u{1b}[0m"),
+            ("$fact:#FF8800:bg_color4!", "\u{1b}[44;33mfact1\u{1b}[0m!"),
+            ("$title|$fact", "|fact1"),
+            ("$fact: ok", "fact1: ok"),
+            ("$fact:1", "fact1:1"),
+            ("$fact:Red", "fact1:Red"),
+            ("__a__\n__b__:blue", "a\n\u{1b}[34mb\u{1b}[0m"),
+            ("__a\nb__", "__a\nb__"),
+        ] {
+            assert_eq!(
+                Template::parse(template)
+                    .unwrap()
+                    .render(&values, ColorDepth::Basic),
+                expected,
+                "{}",
+                template
+            );
+        }
+
+        // The values are not parsed
+        let template = Template::parse("$fact:red").unwrap();
+        assert_eq!(
+            template.render(
+                &Values::from([("fact", "a __b__:blue $fact {$title}".to_string())]),
+                ColorDepth::Basic
+            ),
+            "\u{1b}[31ma __b__:blue $fact {$title}\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn test_has_variable() {
+        let template = Template::parse("__|>__:cyan {$fact:yellow:bold}").unwrap();
+        assert!(template.has_variable("fact"));
+        assert!(!template.has_variable("title"));
+    }
+}
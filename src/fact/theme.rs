@@ -0,0 +1,285 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    layout,
+    style::{Color, Style},
+    template::{Span, Template, Values},
+};
+
+// The width of the box and of the banner when the output is not a terminal
+const DEFAULT_WIDTH: usize = 80;
+const BORDER: Style = Style::Foreground(Color::Basic(6));
+const FACT: Style = Style::Foreground(Color::Basic(3));
+
+// How a fact is laid out, the template is only used by the template theme
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Template,
+    // The fact in a box titled with its provider
+    Box,
+    // The provider and the fact on a single line
+    Compact,
+    // A header line with the provider and the date above the fact
+    Banner,
+}
+
+pub const THEMES: &[Theme] = &[Theme::Template, Theme::Box, Theme::Compact, Theme::Banner];
+
+impl Theme {
+    pub fn is_default(&self) -> bool {
+        *self == Theme::default()
+    }
+
+    pub fn get_names() -> Vec<&'static str> {
+        THEMES.iter().map(|t| t.get_name()).collect()
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            Theme::Template => "template",
+            Theme::Box => "box",
+            Theme::Compact => "compact",
+            Theme::Banner => "banner",
+        }
+    }
+
+    pub fn render(
+        &self,
+        template: &Template,
+        values: &Values,
+        width: Option<usize>,
+        max_lines: Option<usize>,
+    ) -> Vec<Span> {
+        let get = |name: &str| values.get(name).cloned().unwrap_or_default();
+        let fact = vec![Span::new(&get("fact"), &[FACT], true)];
+        match self {
+            Theme::Template => layout::wrap(&template.render_spans(values), width, max_lines),
+            Theme::Box => render_box(&fact, &get("provider"), width, max_lines),
+            Theme::Compact => {
+                let mut spans = vec![];
+                if !get("provider").is_empty() {
+                    spans.push(Span::new(&get("provider"), &[Style::Dimmed], false));
+                    spans.push(Span::new(" ", &[], false));
+                }
+                spans.extend(fact);
+                layout::wrap(&spans, width, Some(1))
+            }
+            Theme::Banner => {
+                let width = width.unwrap_or(DEFAULT_WIDTH);
+                let title = [get("provider"), get("date")]
+                    .into_iter()
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<String>>()
+                    .join(" · ");
+                let mut spans = vec![];
+                let mut title_width = 0;
+                if !title.is_empty() {
+                    spans.push(Span::new("━━━ ", &[BORDER], false));
+                    spans.push(Span::new(&title, &[Style::Bold], false));
+                    spans.push(Span::new(" ", &[BORDER], false));
+                    title_width = layout::get_text_width(&title) + 5;
+                }
+                spans.push(Span::new(
+                    &"━".repeat(width.saturating_sub(title_width)),
+                    &[BORDER],
+                    false,
+                ));
+                spans.push(Span::new("\n", &[], false));
+                spans.extend(layout::wrap(&fact, Some(width), max_lines));
+                spans
+            }
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.get_name())
+    }
+}
+
+impl FromStr for Theme {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match THEMES.iter().find(|t| t.get_name() == s) {
+            Some(theme) => Ok(*theme),
+            None => Err(format!(
+                "unknown theme {}, expected one of {}",
+                s,
+                Theme::get_names().join(", ")
+            ))?,
+        }
+    }
+}
+
+// The box is as narrow as its content allows
+fn render_box(
+    fact: &[Span],
+    title: &str,
+    width: Option<usize>,
+    max_lines: Option<usize>,
+) -> Vec<Span> {
+    let available = width.unwrap_or(DEFAULT_WIDTH).saturating_sub(4).max(1);
+    let lines = layout::split_lines(&layout::wrap(fact, Some(available), max_lines));
+    let title_width = layout::get_text_width(title);
+    let inner = lines
+        .iter()
+        .map(|l| layout::get_spans_width(l))
+        .chain([if title.is_empty() { 0 } else { title_width + 3 }])
+        .max()
+        .unwrap_or(0)
+        .min(available.max(title_width + 3));
+
+    let mut spans = vec![];
+    if title.is_empty() {
+        spans.push(Span::new(
+            &format!("╭{}╮\n", "─".repeat(inner + 2)),
+            &[BORDER],
+            false,
+        ));
+    } else {
+        spans.push(Span::new("╭─ ", &[BORDER], false));
+        spans.push(Span::new(title, &[Style::Bold], false));
+        spans.push(Span::new(
+            &format!(
+                " {}╮\n",
+                "─".repeat((inner + 2).saturating_sub(title_width + 3))
+            ),
+            &[BORDER],
+            false,
+        ));
+    }
+    for line in lines {
+        let padding = inner.saturating_sub(layout::get_spans_width(&line));
+        spans.push(Span::new("│ ", &[BORDER], false));
+        spans.extend(line);
+        spans.push(Span::new(&" ".repeat(padding), &[], false));
+        spans.push(Span::new(" │\n", &[BORDER], false));
+    }
+    spans.push(Span::new(
+        &format!("╰{}╯", "─".repeat(inner + 2)),
+        &[BORDER],
+        false,
+    ));
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The styles are left out
+    fn render(
+        theme: Theme,
+        values: &Values,
+        width: Option<usize>,
+        max_lines: Option<usize>,
+    ) -> String {
+        let template = Template::parse("|> $fact").unwrap();
+        theme
+            .render(&template, values, width, max_lines)
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    fn get_values() -> Values {
+        Values::from([
+            (
+                "fact",
+                "Today I learned that koalas have fingerprints".to_string(),
+            ),
+            ("provider", "TIL".to_string()),
+            ("date", "2023-05-01".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_render() {
+        let values = get_values();
+        for (theme, width, max_lines, expected) in [
+            (
+                Theme::Template,
+                Some(30),
+                None,
+                "|> Today I learned that koalas\n   have fingerprints",
+            ),
+            (
+                Theme::Box,
+                None,
+                None,
+                "╭─ TIL ─────────────────────────────────────────╮\n│ Today I learned that koalas have fingerprints │\n╰───────────────────────────────────────────────╯",
+            ),
+            (
+                Theme::Box,
+                Some(30),
+                Some(2),
+                "╭─ TIL ────────────────────╮\n│ Today I learned that     │\n│ koalas have fingerprints │\n╰──────────────────────────╯",
+            ),
+            (
+                Theme::Box,
+                Some(30),
+                Some(1),
+                "╭─ TIL ─────────────────╮\n│ Today I learned that… │\n╰───────────────────────╯",
+            ),
+            (
+                Theme::Compact,
+                Some(30),
+                None,
+                "TIL Today I learned that…",
+            ),
+            (
+                Theme::Compact,
+                None,
+                None,
+                "TIL Today I learned that koalas have fingerprints",
+            ),
+            (
+                Theme::Banner,
+                Some(30),
+                None,
+                "━━━ TIL · 2023-05-01 ━━━━━━━━━\nToday I learned that koalas\nhave fingerprints",
+            ),
+        ] {
+            assert_eq!(
+                render(theme, &values, width, max_lines),
+                expected,
+                "{} {:?}",
+                theme,
+                width
+            );
+        }
+
+        let values = Values::from([("fact", "Stay tuned".to_string())]);
+        assert_eq!(
+            render(Theme::Box, &values, None, None),
+            "╭────────────╮\n│ Stay tuned │\n╰────────────╯"
+        );
+        assert_eq!(render(Theme::Compact, &values, None, None), "Stay tuned");
+        assert_eq!(
+            render(Theme::Banner, &values, Some(12), None),
+            "━━━━━━━━━━━━\nStay tuned"
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Theme::from_str("box").unwrap(), Theme::Box);
+        match Theme::from_str("card") {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "unknown theme card, expected one of template, box, compact, banner"
+            ),
+        }
+    }
+}
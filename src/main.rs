@@ -1,11 +1,20 @@
-use std::process::exit;
+use std::{
+    cell::OnceCell,
+    error::Error,
+    io::{self, Write},
+    path::Path,
+    process::{self, exit},
+    sync::Arc,
+    time::Duration,
+};
 
-use structopt::StructOpt;
-use third_part::Crawler;
+use structopt::{clap, StructOpt};
+use third_part::{http, Crawler};
 
 mod config;
 mod daemon;
 mod db;
+mod doctor;
 mod fact;
 mod shell;
 mod third_part;
@@ -18,10 +27,17 @@ mod third_part;
     version = "0.0.1"
 )]
 struct Cultura {
+    #[structopt(
+        long = "config",
+        global = true,
+        help = "A config file overriding the settings of the config file and of the environment"
+    )]
+    config_file: Option<String>,
     #[structopt(subcommand)]
     command: Command,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(StructOpt, Debug)]
 enum Command {
     #[structopt(name = "fact", about = "Manage fact")]
@@ -34,12 +50,41 @@ enum Command {
     ConfigRoot(Config),
     #[structopt(name = "doctor", about = "Troubleshoot issues with cultura")]
     DoctorRoot(Doctor),
+    #[structopt(about = "Generate the completions of a shell")]
+    Completions {
+        #[structopt(possible_values = &clap::Shell::variants(), case_insensitive = true)]
+        shell: clap::Shell,
+    },
 }
 
 #[derive(StructOpt, Debug)]
 enum Fact {
     #[structopt(about = "Generate a random fact")]
-    GenerateRandom {},
+    GenerateRandom {
+        #[structopt(flatten)]
+        color: Color,
+    },
+    #[structopt(about = "Harvest facts from the providers once")]
+    Harvest {
+        #[structopt(flatten)]
+        color: Color,
+    },
+    #[structopt(about = "List the facts merged as near-duplicates of an existing fact")]
+    Duplicates {
+        #[structopt(flatten)]
+        color: Color,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct Color {
+    #[structopt(
+        long,
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"],
+        help = "Color the output, auto colors it when it is a terminal unless NO_COLOR is set"
+    )]
+    color: fact::style::ColorChoice,
 }
 
 #[derive(StructOpt, Debug)]
@@ -64,65 +109,181 @@ enum Shell {
 enum Config {
     #[structopt(about = "Define the providers to enable")]
     SetProviders { providers: Vec<String> },
+    #[structopt(about = "Add, remove, enable or disable a provider")]
+    Providers(Providers),
     #[structopt(about = "Define the template to use to display a fact")]
     SetTemplate { template: String },
+    #[structopt(about = "Check a template, the defined one by default, and preview it")]
+    CheckTemplate { template: Option<String> },
+    #[structopt(about = "Define the theme to use to display a fact")]
+    SetTheme {
+        #[structopt(possible_values = &fact::theme::Theme::get_names())]
+        theme: fact::theme::Theme,
+    },
+    #[structopt(about = "Preview every theme with a sample fact")]
+    PreviewThemes {},
+    #[structopt(about = "Activate a profile of the config, no name deactivates it")]
+    UseProfile { name: Option<String> },
+    #[structopt(about = "Print the value of a setting, the overrides included")]
+    Get {
+        #[structopt(
+            possible_values = &config::schema::get_paths(),
+            hide_possible_values = true,
+            help = "The dotted path of the setting, scheduler.interval for instance"
+        )]
+        key: String,
+    },
+    #[structopt(about = "Define a setting in the config file, it is saved only if valid")]
+    Set {
+        #[structopt(
+            possible_values = &config::schema::get_paths(),
+            hide_possible_values = true,
+            help = "The dotted path of the setting, scheduler.interval for instance"
+        )]
+        key: String,
+        #[structopt(help = "The value of the setting, a comma separated list for the lists")]
+        value: String,
+    },
+    #[structopt(about = "Remove a setting from the config file to use its default value")]
+    Unset {
+        #[structopt(
+            possible_values = &config::schema::get_paths(),
+            hide_possible_values = true,
+            help = "The dotted path of the setting, scheduler.interval for instance"
+        )]
+        key: String,
+    },
     #[structopt(about = "Dump the current config")]
-    Dump {},
+    Dump {
+        #[structopt(long, help = "Show where every setting comes from")]
+        show_origin: bool,
+    },
     #[structopt(about = "Get the path of the config file")]
     GetConfigFilePath {},
+    #[structopt(about = "Edit the config file with $VISUAL or $EDITOR, it is saved only if valid")]
+    Edit {},
+}
+
+#[derive(StructOpt, Debug)]
+enum Providers {
+    #[structopt(about = "List every available provider with its state")]
+    List {},
+    #[structopt(about = "Add a provider with its default settings")]
+    Add { provider: String },
+    #[structopt(about = "Remove a provider and its settings")]
+    Remove { provider: String },
+    #[structopt(about = "Harvest again a disabled provider")]
+    Enable { provider: String },
+    #[structopt(about = "Stop harvesting a provider, its settings are kept")]
+    Disable { provider: String },
 }
 
 #[derive(StructOpt, Debug)]
 enum Doctor {
     #[structopt(about = "Stop the daemon and remove all cultura config and data")]
     Reset {},
+    #[structopt(about = "Check the whole environment of cultura and give hints to fix issues")]
+    Check {},
     #[structopt(about = "Check if providers are working by performing a call with them")]
-    RunProviders {},
+    RunProviders {
+        #[structopt(long = "provider", help = "Run only this provider, can be repeated")]
+        providers: Vec<String>,
+        #[structopt(long, help = "Output the report as JSON")]
+        json: bool,
+        #[structopt(
+            long,
+            parse(try_from_str = humantime::parse_duration),
+            help = "The maximum duration of a provider run, default is the harvest deadline"
+        )]
+        timeout: Option<Duration>,
+    },
+    #[structopt(about = "Save the page crawled by a provider as a test fixture")]
+    RecordFixture {
+        provider: String,
+        #[structopt(
+            long,
+            help = "The file to write, default is src/third_part/fixtures/<provider>.html in the cultura sources"
+        )]
+        output: Option<String>,
+    },
+    #[structopt(about = "Show the outcome of the previous harvests of every provider")]
+    ProvidersHealth {},
+    #[structopt(about = "Preview a fact before and after the normalisation of a provider")]
+    PreviewNormalisation { provider: String, fact: String },
 }
 
 fn main() {
     let a = Cultura::from_args();
 
-    let config_resolver_result = config::ConfigResolver::new(home::home_dir());
-    if config_resolver_result.is_err() {
-        eprintln!(
-            "cannot bootstrap the config: {}",
-            config_resolver_result.err().unwrap()
-        );
-        exit(0);
-    }
-    let config_resolver = config_resolver_result.unwrap();
-
-    let third_part_services: Vec<Box<dyn Crawler>> = config_resolver.get_providers();
+    let locations = match config::Locations::from_env(home::home_dir()) {
+        Ok(locations) => locations,
+        Err(e) => {
+            eprintln!("cannot resolve the cultura directories: {}", e);
+            exit(0);
+        }
+    };
 
-    let fact_repository_result = crate::db::Fact::new(&config_resolver.get_database_path());
-    if fact_repository_result.is_err() {
-        eprintln!(
-            "cannot bootstrap the fact repository: {}",
-            fact_repository_result.err().unwrap()
-        );
-        exit(0);
-    }
-    let fact_repository = fact_repository_result.unwrap();
-    let fact_service = fact::Fact::new(&config_resolver, &fact_repository, third_part_services);
+    // The services are bootstrapped by the first command using them, the
+    // commands troubleshooting the environment must run even if it fails
+    let bootstrapped = OnceCell::new();
+    let services = || bootstrapped.get_or_init(|| bootstrap(&locations, a.config_file.as_deref()));
 
     match a.command {
-        Command::FactRoot(provider) => match provider {
-            Fact::GenerateRandom {} => match fact_service.print_random() {
-                Ok(_) => (),
-                Err(e) => eprintln!("an error occurred when printing fact: {}", e),
-            },
-        },
+        Command::FactRoot(fact) => {
+            let (Fact::GenerateRandom { color }
+            | Fact::Harvest { color }
+            | Fact::Duplicates { color }) = &fact;
+            colored::control::set_override(color.color.should_colorize());
+            match fact {
+                Fact::GenerateRandom { color } => match services()
+                    .get_fact_service()
+                    .print_random(color.color.get_color_depth())
+                {
+                    Ok(_) => (),
+                    Err(e) => eprintln!("an error occurred when printing fact: {}", e),
+                },
+                Fact::Harvest { .. } => match services().get_fact_service().update() {
+                    Ok(stats) => stats.iter().for_each(|s| println!("{}", s)),
+                    Err(e) => eprintln!("an error occurred when harvesting facts: {}", e),
+                },
+                Fact::Duplicates { .. } => match services().fact_repository.get_duplicates() {
+                    Ok(duplicates) => duplicates.iter().for_each(|d| {
+                        println!(
+                            r#"{} ({})
+  Merged into {} ({})
+  Similarity {:.2}, on {}
+---
+"#,
+                            d.fact,
+                            d.provider,
+                            d.original_fact,
+                            d.original_provider,
+                            d.similarity,
+                            d.created_at
+                        )
+                    }),
+                    Err(e) => eprintln!("an error occurred when listing duplicates: {}", e),
+                },
+            }
+        }
         Command::DaemonRoot(daemon) => match daemon {
             Daemon::Start { run_in_foreground } => {
-                match daemon::Daemon::new(&config_resolver, &fact_service)
-                    .start(run_in_foreground.unwrap_or_default())
+                match daemon::Daemon::new(
+                    &services().config_resolver,
+                    &services().get_fact_service(),
+                )
+                .start(run_in_foreground.unwrap_or_default())
                 {
                     Ok(_) => (),
                     Err(e) => eprintln!("cannot start daemon: {}", e),
                 }
             }
-            Daemon::Stop {} => match daemon::Daemon::new(&config_resolver, &fact_service).stop() {
+            Daemon::Stop {} => match daemon::Daemon::new(
+                &services().config_resolver,
+                &services().get_fact_service(),
+            )
+            .stop()
+            {
                 Ok(_) => println!("daemon stopped"),
                 Err(e) => eprintln!("cannot stop daemon: {}", e),
             },
@@ -136,52 +297,405 @@ fn main() {
             }
         }
         Command::ConfigRoot(conf) => match conf {
-            Config::Dump {} => {
-                println!("{}", config_resolver.get_config())
+            Config::Dump { show_origin } => {
+                if show_origin {
+                    match services().config_resolver.dump_with_origin() {
+                        Ok(dump) => println!("{}", dump),
+                        Err(e) => eprintln!("cannot dump the config: {}", e),
+                    }
+                } else {
+                    println!("{}", services().config_resolver.get_config())
+                }
             }
             Config::GetConfigFilePath {} => {
                 println!(
                     "The config file path is located at {}",
-                    config_resolver.get_config_file_path()
+                    services().config_resolver.get_config_file_path()
                 )
             }
-            Config::SetProviders { providers } => match config_resolver.set_providers(providers) {
-                Ok(_) => {
-                    println!("providers defined");
+            Config::SetProviders { providers } => {
+                match services().config_resolver.set_providers(providers) {
+                    Ok(_) => match services().config_resolver.get_active_profile() {
+                        Some(name) => println!("providers defined in the profile {}", name),
+                        None => println!("providers defined"),
+                    },
+                    Err(e) => eprintln!("cannot set the providers: {}", e),
+                }
+            }
+            Config::Providers(providers) => match providers {
+                Providers::List {} => services().config_resolver.list_providers().iter().for_each(
+                    |(provider, state)| {
+                        println!(
+                            "{:<8} {:<9} {}",
+                            provider.get_id(),
+                            state,
+                            provider.get_description()
+                        )
+                    },
+                ),
+                Providers::Add { provider } => print_provider_update(
+                    services().config_resolver.add_provider(&provider),
+                    &provider,
+                    "added",
+                ),
+                Providers::Remove { provider } => print_provider_update(
+                    services().config_resolver.remove_provider(&provider),
+                    &provider,
+                    "removed",
+                ),
+                Providers::Enable { provider } => print_provider_update(
+                    services().config_resolver.enable_provider(&provider),
+                    &provider,
+                    "enabled",
+                ),
+                Providers::Disable { provider } => print_provider_update(
+                    services().config_resolver.disable_provider(&provider),
+                    &provider,
+                    "disabled",
+                ),
+            },
+            Config::UseProfile { name } => {
+                match services().config_resolver.use_profile(name.clone()) {
+                    Ok(_) => match name {
+                        Some(name) => println!("profile {} activated", name),
+                        None => println!("profile deactivated"),
+                    },
+                    Err(e) => eprintln!("cannot use the profile: {}", e),
+                }
+            }
+            Config::SetTemplate { template } => {
+                match services().config_resolver.set_template(template) {
+                    Ok(_) => match services().config_resolver.get_active_profile() {
+                        Some(name) => println!("template defined in the profile {}", name),
+                        None => println!("template defined"),
+                    },
+                    Err(e) => eprintln!("cannot set the template: {}", e),
+                }
+            }
+            Config::CheckTemplate { template } => {
+                match fact::preview_template(
+                    &template.unwrap_or_else(|| services().config_resolver.get_template()),
+                    fact::style::ColorChoice::Auto.get_color_depth(),
+                ) {
+                    Ok(preview) => println!("{}", preview),
+                    Err(e) => {
+                        eprintln!("invalid template: {}", e);
+                        exit(1);
+                    }
+                }
+            }
+            Config::SetTheme { theme } => match services().config_resolver.set_theme(theme) {
+                Ok(_) => println!("theme defined"),
+                Err(e) => {
+                    eprintln!("cannot set the theme: {}", e);
+                    exit(1);
+                }
+            },
+            Config::PreviewThemes {} => match fact::preview_themes(
+                &services().config_resolver.get_template(),
+                &services().config_resolver.get_layout(),
+                fact::style::ColorChoice::Auto.get_color_depth(),
+            ) {
+                Ok(previews) => {
+                    let current = services().config_resolver.get_theme();
+                    for (theme, preview) in previews {
+                        let name = if theme == current {
+                            format!("{} (current)", theme)
+                        } else {
+                            theme.to_string()
+                        };
+                        println!("{}\n{}\n", name, preview.trim_matches('\n'));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("cannot preview the themes: {}", e);
+                    exit(1);
+                }
+            },
+            Config::Get { key } => match services().config_resolver.get_value(&key) {
+                Ok(Some(toml::Value::String(s))) => println!("{}", s),
+                Ok(Some(value)) => println!("{}", value),
+                // Like git config, nothing is printed for an unset key
+                Ok(None) => exit(1),
+                Err(e) => {
+                    eprintln!("cannot get {}: {}", key, e);
+                    exit(1);
+                }
+            },
+            Config::Set { key, value } => {
+                match services().config_resolver.set_value(&key, &value) {
+                    Ok(_) => println!("{} defined", key),
+                    Err(e) => {
+                        eprintln!("cannot set {}: {}", key, e);
+                        exit(1);
+                    }
+                }
+            }
+            Config::Unset { key } => match services().config_resolver.unset_value(&key) {
+                Ok(_) => println!("{} removed", key),
+                Err(e) => {
+                    eprintln!("cannot unset {}: {}", key, e);
+                    exit(1);
                 }
-                Err(e) => eprintln!("cannot set the providers: {}", e),
             },
-            Config::SetTemplate { template } => match config_resolver.set_template(template) {
-                Ok(_) => {
-                    println!("template defined");
+            // An invalid config file can be fixed by editing it
+            Config::Edit {} => match config::edit(&locations, run_editor, ask_to_retry) {
+                Ok(true) => println!("config saved"),
+                Ok(false) => println!("config left unchanged"),
+                Err(e) => {
+                    eprintln!("cannot edit the config: {}", e);
+                    exit(1);
                 }
-                Err(e) => eprintln!("cannot set the template: {}", e),
             },
         },
         Command::DoctorRoot(doctor) => match doctor {
             Doctor::Reset {} => {
-                match daemon::Daemon::new(&config_resolver, &fact_service).stop() {
+                match daemon::Daemon::new(
+                    &services().config_resolver,
+                    &services().get_fact_service(),
+                )
+                .stop()
+                {
                     Ok(_) => println!("* stop the daemon"),
                     Err(e) => eprintln!("cannot stop daemon: {}", e),
                 }
-                match config_resolver.clear_all() {
+                match services().config_resolver.clear_all() {
                     Ok(_) => println!("* config folder deleted"),
                     Err(e) => eprintln!("cannot remove the config folder: {}", e),
                 }
             }
-            Doctor::RunProviders {} => third_part::get_available_providers().iter().for_each(|i| {
-                println!(
-                    r#"Fetching provider {}
-  Found {} facts
-  Details
-    {:?}
----
-"#,
-                    i.0,
-                    i.1.get_facts().unwrap().len(),
-                    i.1.get_facts().unwrap()
-                )
-            }),
+            Doctor::Check {} => {
+                let report = doctor::check::run_checks(
+                    &locations,
+                    a.config_file.as_deref(),
+                    home::home_dir(),
+                    std::env::var("SHELL").ok(),
+                );
+                println!("{}", report.to_table());
+                if !report.is_success() {
+                    exit(1);
+                }
+            }
+            Doctor::RunProviders {
+                providers,
+                json,
+                timeout,
+            } => {
+                let providers = match doctor::select_providers(&providers) {
+                    Ok(providers) => providers,
+                    Err(e) => {
+                        eprintln!("cannot select the providers: {}", e);
+                        exit(1);
+                    }
+                };
+                // The cache is bypassed to always check the parsing of a page
+                let client =
+                    match http::Client::new(&services().config_resolver.get_network(), None) {
+                        Ok(client) => Arc::new(client),
+                        Err(e) => {
+                            eprintln!("cannot bootstrap the http client: {}", e);
+                            exit(1);
+                        }
+                    };
+                let report = doctor::run_providers(
+                    &providers,
+                    client,
+                    timeout.unwrap_or(services().config_resolver.get_harvest().deadline),
+                );
+                if json {
+                    match report.to_json() {
+                        Ok(json) => println!("{}", json),
+                        Err(e) => eprintln!("cannot generate the report: {}", e),
+                    }
+                } else {
+                    println!("{}", report.to_table());
+                }
+                if !report.is_success() {
+                    exit(1);
+                }
+            }
+            Doctor::RecordFixture { provider, output } => {
+                let provider = match doctor::select_providers(&[provider]) {
+                    Ok(mut providers) => providers.remove(0),
+                    Err(e) => {
+                        eprintln!("cannot select the provider: {}", e);
+                        exit(1);
+                    }
+                };
+                // The fixtures of the sources the binary was built from
+                let output = output.unwrap_or(format!(
+                    "{}/src/third_part/fixtures/{}.html",
+                    env!("CARGO_MANIFEST_DIR"),
+                    provider.get_id().to_lowercase()
+                ));
+                // The cache is bypassed to always get the page
+                match http::Client::new(&services().config_resolver.get_network(), None)
+                    .and_then(|client| doctor::record_fixture(provider.as_ref(), &client, &output))
+                {
+                    Ok(size) => println!("{} bytes recorded in {}", size, output),
+                    Err(e) => {
+                        eprintln!("cannot record the fixture: {}", e);
+                        exit(1);
+                    }
+                }
+            }
+            Doctor::ProvidersHealth {} => match services().fact_repository.get_providers_health() {
+                Ok(healths) if healths.is_empty() => println!("No harvest recorded yet"),
+                Ok(healths) => {
+                    println!(
+                        "{:<10} {:>8} {:>8} {:>17}  {:<36} {:>10} {:>6} {:>8}  Last error",
+                        "Provider",
+                        "Attempts",
+                        "Success",
+                        "Failures in a row",
+                        "Last attempt",
+                        "Duration",
+                        "Found",
+                        "Inserted",
+                    );
+                    healths.iter().for_each(|h| {
+                        println!(
+                            "{:<10} {:>8} {:>7}% {:>17}  {:<36} {:>8}ms {:>6} {:>8}  {}",
+                            h.provider,
+                            h.attempts,
+                            h.successes * 100 / h.attempts.max(1),
+                            h.consecutive_failures,
+                            h.last_attempt_at,
+                            h.last_duration_ms,
+                            h.last_found,
+                            h.last_inserted,
+                            h.last_error.clone().unwrap_or("-".to_string()),
+                        )
+                    })
+                }
+                Err(e) => eprintln!("cannot get the health of the providers: {}", e),
+            },
+            Doctor::PreviewNormalisation { provider, fact } => {
+                let steps = services()
+                    .config_resolver
+                    .get_normalisation_steps(&provider);
+                match fact::normaliser::Normaliser::new(&steps) {
+                    Ok(normaliser) => println!(
+                        r#"Normalising with provider {}
+  Steps
+    {}
+  Before
+    {}
+  After
+    {}"#,
+                        provider,
+                        steps
+                            .iter()
+                            .map(|s| s.to_string())
+                            .collect::<Vec<String>>()
+                            .join("\n    "),
+                        fact,
+                        normaliser.normalise(&fact)
+                    ),
+                    Err(e) => eprintln!("cannot build the normalisation steps: {}", e),
+                }
+            }
         },
+        Command::Completions { shell } => {
+            Cultura::clap().gen_completions_to("cultura", shell, &mut io::stdout())
+        }
+    }
+}
+
+// What the commands need once the config is loaded
+struct Services {
+    config_resolver: config::ConfigResolver,
+    fact_repository: db::Fact,
+    http_client: Arc<http::Client>,
+}
+
+impl Services {
+    fn get_fact_service(&self) -> fact::Fact<'_> {
+        let third_part_services: Vec<Box<dyn Crawler>> = self.config_resolver.get_providers();
+        fact::Fact::new(
+            &self.config_resolver,
+            &self.fact_repository,
+            self.http_client.clone(),
+            third_part_services,
+        )
+    }
+}
+
+fn bootstrap(locations: &config::Locations, config_file: Option<&str>) -> Services {
+    let config_resolver_result = config::ConfigResolver::new(locations.clone())
+        .and_then(|c| c.with_overrides(config::load_overrides(config_file)?));
+    if config_resolver_result.is_err() {
+        eprintln!(
+            "cannot bootstrap the config: {}",
+            config_resolver_result.err().unwrap()
+        );
+        exit(0);
+    }
+    let config_resolver = config_resolver_result.unwrap();
+
+    let fact_repository_result = crate::db::Fact::new(&config_resolver.get_database_path());
+    if fact_repository_result.is_err() {
+        eprintln!(
+            "cannot bootstrap the fact repository: {}",
+            fact_repository_result.err().unwrap()
+        );
+        exit(0);
+    }
+    let fact_repository = fact_repository_result.unwrap();
+
+    let http_client_result = crate::db::HttpCache::new(&config_resolver.get_database_path())
+        .and_then(|cache| http::Client::new(&config_resolver.get_network(), Some(cache)));
+    if http_client_result.is_err() {
+        eprintln!(
+            "cannot bootstrap the http client: {}",
+            http_client_result.err().unwrap()
+        );
+        exit(0);
+    }
+    let http_client = Arc::new(http_client_result.unwrap());
+
+    Services {
+        config_resolver,
+        fact_repository,
+        http_client,
+    }
+}
+
+fn print_provider_update(result: Result<(), Box<dyn Error>>, provider: &str, action: &str) {
+    match result {
+        Ok(_) => println!("{} {}", provider, action),
+        Err(e) => {
+            eprintln!("cannot update the providers: {}", e);
+            exit(1);
+        }
+    }
+}
+
+fn run_editor(path: &Path) -> Result<(), Box<dyn Error>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    // The editor can come with arguments, code --wait for instance
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        Err(format!("{} exited with {}", editor, status))?
+    }
+    Ok(())
+}
+
+fn ask_to_retry(issues: &[config::Issue]) -> bool {
+    eprintln!("the config is invalid:");
+    issues.iter().for_each(|i| eprintln!("  {}", i));
+    eprint!("edit it again? [Y/n] ");
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => !answer.trim().to_lowercase().starts_with('n'),
     }
 }
@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Certificate, NoProxy, Proxy, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{CachedPage, HttpCache};
+
+const USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 6.1; Win64; x64; rv:47.0) Gecko/20100101 Firefox/47.0";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+    pub proxy: Option<String>,
+    pub no_proxy: Vec<String>,
+    #[serde(with = "humantime_serde")]
+    pub connect_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub user_agent: String,
+    pub root_certificates: Vec<String>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network {
+            proxy: None,
+            no_proxy: vec![],
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+            user_agent: USER_AGENT.to_string(),
+            root_certificates: vec![],
+        }
+    }
+}
+
+impl Network {
+    pub fn is_default(&self) -> bool {
+        *self == Network::default()
+    }
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "proxy: {}, no_proxy: {:?}, connect_timeout: {}, timeout: {}, user_agent: {}, root_certificates: {:?}",
+            self.proxy.clone().unwrap_or("none".to_string()),
+            self.no_proxy,
+            humantime::format_duration(self.connect_timeout),
+            humantime::format_duration(self.timeout),
+            self.user_agent,
+            self.root_certificates,
+        )
+    }
+}
+
+// Client is shared by all the providers, when a cache is provided the pages
+// are kept with the validators returned by a server to perform conditional
+// requests afterwards.
+pub struct Client {
+    network: Network,
+    // The underlying client runs a background thread that doesn't survive a
+    // fork, it is built on first use so the daemon gets its own.
+    client: OnceLock<reqwest::blocking::Client>,
+    cache: Option<HttpCache>,
+    // The pages downloaded but not cached yet
+    fetched: Mutex<HashMap<String, CachedPage>>,
+}
+
+impl Client {
+    pub fn new(network: &Network, cache: Option<HttpCache>) -> Result<Client, Box<dyn Error>> {
+        // Fail early on a wrong setting
+        build_client(network)?;
+        Ok(Client {
+            network: network.clone(),
+            client: OnceLock::new(),
+            cache,
+            fetched: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Return the body of the page, the cached one when the page didn't
+    // change since it was cached.
+    pub fn get(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        let client = match self.client.get() {
+            Some(client) => client,
+            None => {
+                let client = build_client(&self.network)?;
+                self.client.get_or_init(|| client)
+            }
+        };
+        let mut request = client.get(url);
+        let cached_page = match &self.cache {
+            Some(cache) => cache.get_page(url)?,
+            None => None,
+        };
+        if let Some(page) = &cached_page {
+            if let Some(etag) = &page.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &page.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send()?;
+        if let (StatusCode::NOT_MODIFIED, Some(page)) = (response.status(), cached_page) {
+            return Ok(page.body);
+        }
+        let response = response.error_for_status()?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        let body = response.text()?;
+        if self.cache.is_some() && (etag.is_some() || last_modified.is_some()) {
+            self.fetched.lock().map_err(|e| e.to_string())?.insert(
+                url.to_string(),
+                CachedPage {
+                    etag,
+                    last_modified,
+                    body: body.to_owned(),
+                },
+            );
+        }
+        Ok(body)
+    }
+
+    // Forget the pages not saved, the ones of the crawlers which failed or
+    // passed the deadline, so they don't pile up in a long running daemon.
+    pub fn discard_fetched(&self) -> Result<(), Box<dyn Error>> {
+        self.fetched.lock().map_err(|e| e.to_string())?.clear();
+        Ok(())
+    }
+
+    // Cache the page downloaded from the url, it is called once the facts of
+    // the page are stored so a page failing to be stored is downloaded again.
+    pub fn save(&self, url: &str) -> Result<(), Box<dyn Error>> {
+        let page = self.fetched.lock().map_err(|e| e.to_string())?.remove(url);
+        match (&self.cache, page) {
+            (Some(cache), Some(page)) => cache.save_page(url, &page),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn build_client(network: &Network) -> Result<reqwest::blocking::Client, Box<dyn Error>> {
+    let mut builder = reqwest::blocking::Client::builder()
+        .user_agent(network.user_agent.as_str())
+        .connect_timeout(network.connect_timeout)
+        .timeout(network.timeout);
+    if let Some(proxy) = &network.proxy {
+        builder = builder
+            .proxy(Proxy::all(proxy)?.no_proxy(NoProxy::from_string(&network.no_proxy.join(","))));
+    }
+    for path in &network.root_certificates {
+        let certificate =
+            fs::read(path).map_err(|e| format!("cannot read the certificate {}: {}", path, e))?;
+        builder = builder.add_root_certificate(Certificate::from_pem(&certificate)?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+        time::Instant,
+    };
+
+    use rand::{distributions::Alphanumeric, Rng};
+
+    use super::*;
+
+    // Serve a page having an etag and numbered by the request, a 304 is
+    // returned when the etag is sent back.
+    fn start_server(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(requests).enumerate() {
+                let mut stream = stream.unwrap();
+                let headers = BufReader::new(&stream)
+                    .lines()
+                    .map(|l| l.unwrap())
+                    .take_while(|l| !l.is_empty())
+                    .collect::<Vec<String>>();
+                let response = if headers
+                    .iter()
+                    .any(|h| h.to_lowercase() == r#"if-none-match: "v1""#)
+                {
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    format!("HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\nConnection: close\r\n\r\nbody{}", i)
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}/page", address)
+    }
+
+    #[test]
+    fn test_get_with_a_cache() {
+        let database_name = format!(
+            "http-{}.sqlite",
+            rand::thread_rng()
+                .sample_iter(Alphanumeric)
+                .take(8)
+                .map(char::from)
+                .collect::<String>()
+        );
+        let url = start_server(4);
+        let client = Client::new(
+            &Network::default(),
+            Some(HttpCache::new(&database_name).unwrap()),
+        )
+        .unwrap();
+
+        // The page is cached only once saved, a discarded page is not
+        assert_eq!(client.get(&url).unwrap(), "body0");
+        client.discard_fetched().unwrap();
+        client.save(&url).unwrap();
+        assert_eq!(client.get(&url).unwrap(), "body1");
+        client.save(&url).unwrap();
+        assert_eq!(client.get(&url).unwrap(), "body1");
+        assert_eq!(client.get(&url).unwrap(), "body1");
+    }
+
+    #[test]
+    fn test_get_without_cache() {
+        let url = start_server(2);
+        let client = Client::new(&Network::default(), None).unwrap();
+
+        client.get(&url).unwrap();
+        client.save(&url).unwrap();
+        assert_eq!(client.get(&url).unwrap(), "body1");
+    }
+
+    #[test]
+    fn test_get_through_a_proxy() {
+        let proxy = start_server(1).replace("/page", "");
+        let client = Client::new(
+            &Network {
+                proxy: Some(proxy),
+                no_proxy: vec!["localhost".to_string()],
+                ..Network::default()
+            },
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(client.get("http://cultura.invalid/page").unwrap(), "body0");
+    }
+
+    #[test]
+    fn test_get_with_a_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _streams = listener.incoming().take(1).collect::<Vec<_>>();
+            thread::sleep(Duration::from_secs(5));
+        });
+        let client = Client::new(
+            &Network {
+                timeout: Duration::from_millis(200),
+                ..Network::default()
+            },
+            None,
+        )
+        .unwrap();
+
+        let now = Instant::now();
+        assert!(client.get(&url).is_err());
+        assert!(now.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_new_with_a_missing_certificate() {
+        match Client::new(
+            &Network {
+                root_certificates: vec!["/a/missing/certificate.pem".to_string()],
+                ..Network::default()
+            },
+            None,
+        ) {
+            Ok(_) => panic!("must return an error"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("cannot read the certificate /a/missing/certificate.pem")),
+        }
+    }
+}
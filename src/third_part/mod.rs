@@ -2,15 +2,48 @@ use std::{collections::HashMap, error::Error};
 
 use dyn_clone::DynClone;
 
+// A fact found by a crawler with what is known about it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Item {
+    pub fact: String,
+    pub source_url: Option<String>,
+    pub title: Option<String>,
+}
+
+impl From<String> for Item {
+    fn from(fact: String) -> Self {
+        Item {
+            fact,
+            ..Item::default()
+        }
+    }
+}
+
+impl From<&str> for Item {
+    fn from(fact: &str) -> Self {
+        Item::from(fact.to_string())
+    }
+}
+
 #[typetag::serde(tag = "provider")]
-pub trait Crawler: DynClone {
-    fn get_facts(&self) -> Result<Vec<String>, Box<dyn Error>>;
+pub trait Crawler: DynClone + Send + Sync {
+    fn get_facts(&self, client: &http::Client) -> Result<Vec<Item>, Box<dyn Error>>;
     fn get_id(&self) -> String;
+    // A one line description shown when listing the providers
+    fn get_description(&self) -> String;
+    // The page crawled to harvest the facts, if any
+    fn get_url(&self) -> Option<String> {
+        None
+    }
 }
 
 dyn_clone::clone_trait_object!(Crawler);
 
+pub mod http;
 pub mod reddit;
+pub mod starter;
+#[cfg(test)]
+pub mod testing;
 pub mod wikipedia;
 
 pub fn get_available_providers() -> HashMap<String, Box<dyn Crawler>> {
@@ -18,6 +51,7 @@ pub fn get_available_providers() -> HashMap<String, Box<dyn Crawler>> {
     let providers: Vec<Box<dyn Crawler>> = vec![
         Box::new(reddit::TIL::new()),
         Box::new(wikipedia::DYK::new()),
+        Box::new(starter::Starter::new()),
     ];
     providers.into_iter().for_each(|p| {
         map_providers.insert(p.get_id(), p);
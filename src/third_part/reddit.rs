@@ -5,46 +5,125 @@ use scraper::Selector;
 use serde::Deserialize;
 use serde::Serialize;
 
-use super::Crawler;
+use super::{http, Crawler, Item};
 
+const URL: &str = "https://www.reddit.com/r/todayilearned/new";
+
+// The url can be overridden in the config to point to a mirror
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TIL {
-    #[serde(skip)]
+    #[serde(default = "get_default_url", skip_serializing_if = "is_default_url")]
     url: String,
 }
 
 impl TIL {
     pub fn new() -> TIL {
         TIL {
-            url: "https://www.reddit.com/r/todayilearned/new".to_string(),
+            url: get_default_url(),
         }
     }
+
+    #[cfg(test)]
+    pub fn with_url(url: String) -> TIL {
+        TIL { url }
+    }
+}
+
+fn get_default_url() -> String {
+    URL.to_string()
+}
+
+fn is_default_url(url: &str) -> bool {
+    url == URL
 }
 
 #[typetag::serde]
 impl Crawler for TIL {
-    fn get_facts(&self) -> Result<Vec<String>, Box<dyn Error>> {
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(
-                "Mozilla/5.0 (Windows NT 6.1; Win64; x64; rv:47.0) Gecko/20100101 Firefox/47.0",
-            )
-            .build()?;
-        let body = client.get(&self.url).send()?;
-        let fragment = Html::parse_document(body.text()?.as_str());
+    fn get_facts(&self, client: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+        let body = client.get(&self.url)?;
+        let url = reqwest::Url::parse(&self.url)?;
+        let fragment = Html::parse_document(body.as_str());
         let selector = Selector::parse(r#"a[data-click-id="body"]"#).unwrap();
 
         Ok(fragment
             .select(&selector)
-            .map(|e| {
-                e.text()
-                    .into_iter()
+            .map(|e| Item {
+                fact: e
+                    .text()
                     .fold(String::new(), |acc: String, e: &str| acc.to_owned() + e)
-                    .replace("TIL", "Today I learned")
+                    .replace("TIL", "Today I learned"),
+                // The link of the post is relative to the page
+                source_url: e
+                    .value()
+                    .attr("href")
+                    .and_then(|href| url.join(href).ok())
+                    .map(|u| u.to_string()),
+                ..Item::default()
             })
-            .collect::<Vec<String>>())
+            .collect::<Vec<Item>>())
     }
 
     fn get_id(&self) -> String {
         "TIL".to_string()
     }
+
+    fn get_description(&self) -> String {
+        "The latest posts of the r/todayilearned subreddit".to_string()
+    }
+
+    fn get_url(&self) -> Option<String> {
+        Some(self.url.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::third_part::testing;
+
+    use super::*;
+
+    const EXPECTED: &[(&str, &str)] = &[
+        (
+            "Today I learned that the first webcam was used to watch a coffee pot at Cambridge",
+            "/r/todayilearned/comments/16x1a2b/",
+        ),
+        (
+            "Today I learned that Scotland has 421 words for snow",
+            "/r/todayilearned/comments/16x1a3c/",
+        ),
+        (
+            "Today I learned the first email was sent in 1971 by Ray Tomlinson",
+            "/r/todayilearned/comments/16x1a4d/",
+        ),
+    ];
+
+    #[test]
+    fn test_get_facts() {
+        let url = testing::serve(include_str!("fixtures/til.html"), 1);
+        let client = http::Client::new(&http::Network::default(), None).unwrap();
+
+        let facts = TIL::with_url(url.clone()).get_facts(&client).unwrap();
+        let origin = url.trim_end_matches("/page");
+        assert_eq!(
+            facts,
+            EXPECTED
+                .iter()
+                .map(|(fact, path)| Item {
+                    source_url: Some(format!("{}{}", origin, path)),
+                    ..Item::from(*fact)
+                })
+                .collect::<Vec<Item>>()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_without_url() {
+        let crawler: Box<dyn Crawler> = toml::from_str(r#"provider = "TIL""#).unwrap();
+        assert_eq!(crawler.get_url(), Some(URL.to_string()));
+        assert_eq!(
+            toml::to_string(&crawler).unwrap().trim(),
+            r#"provider = "TIL""#
+        );
+    }
 }
@@ -0,0 +1,42 @@
+use std::error::Error;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::{http, Crawler, Item};
+
+pub const ID: &str = "STARTER";
+const FACTS: &str = include_str!("starter.txt");
+
+pub fn get_starter_facts() -> Vec<String> {
+    FACTS
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>()
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Starter {}
+
+impl Starter {
+    pub fn new() -> Starter {
+        Starter {}
+    }
+}
+
+#[typetag::serde]
+impl Crawler for Starter {
+    fn get_facts(&self, _: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+        Ok(get_starter_facts().into_iter().map(Item::from).collect())
+    }
+
+    fn get_id(&self) -> String {
+        ID.to_string()
+    }
+
+    fn get_description(&self) -> String {
+        "A curated set of facts shipped with cultura, available offline".to_string()
+    }
+}
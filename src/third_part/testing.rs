@@ -0,0 +1,33 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+
+// Serve the given page to the next requests whatever the path is requested,
+// it stands in for the site of a provider.
+pub fn serve(page: &'static str, requests: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let mut stream = stream.unwrap();
+            BufReader::new(&stream)
+                .lines()
+                .map(|l| l.unwrap())
+                .take_while(|l| !l.is_empty())
+                .for_each(drop);
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        page.len(),
+                        page
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+        }
+    });
+    format!("http://{}/page", address)
+}
@@ -5,42 +5,136 @@ use scraper::Selector;
 use serde::Deserialize;
 use serde::Serialize;
 
-use super::Crawler;
+use super::{http, Crawler, Item};
 
+const URL: &str = "https://en.wikipedia.org/wiki/Wikipedia:Recent_additions";
+
+// The url can be overridden in the config to point to a mirror
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DYK {
-    #[serde(skip)]
+    #[serde(default = "get_default_url", skip_serializing_if = "is_default_url")]
     url: String,
 }
 
 impl DYK {
     pub fn new() -> DYK {
         DYK {
-            url: "https://en.wikipedia.org/wiki/Wikipedia:Recent_additions".to_string(),
+            url: get_default_url(),
         }
     }
+
+    #[cfg(test)]
+    pub fn with_url(url: String) -> DYK {
+        DYK { url }
+    }
+}
+
+fn get_default_url() -> String {
+    URL.to_string()
+}
+
+fn is_default_url(url: &str) -> bool {
+    url == URL
 }
 
 #[typetag::serde]
 impl Crawler for DYK {
-    fn get_facts(&self) -> Result<Vec<String>, Box<dyn Error>> {
-        let body = reqwest::blocking::get(&self.url)?;
+    fn get_facts(&self, client: &http::Client) -> Result<Vec<Item>, Box<dyn Error>> {
+        let body = client.get(&self.url)?;
 
-        let fragment = Html::parse_document(body.text()?.as_str());
+        let url = reqwest::Url::parse(&self.url)?;
+        let fragment = Html::parse_document(body.as_str());
         let selector = Selector::parse(r#"div[id="mw-content-text"] ul li"#).unwrap();
+        // The first article linked by a fact is the one it is about
+        let article_selector = Selector::parse(r#"a[href^="/wiki/"]"#).unwrap();
         Ok(fragment
             .select(&selector)
             .map(|e| {
-                e.text()
-                    .into_iter()
-                    .fold(String::new(), |acc: String, e: &str| acc.to_owned() + e)
+                let article = e.select(&article_selector).next();
+                Item {
+                    fact: e
+                        .text()
+                        .fold(String::new(), |acc: String, e: &str| acc.to_owned() + e),
+                    source_url: article
+                        .and_then(|a| a.value().attr("href"))
+                        .and_then(|href| url.join(href).ok())
+                        .map(|u| u.to_string()),
+                    title: article.map(|a| a.text().collect::<String>()),
+                }
+            })
+            .filter(|i| i.fact.starts_with("..."))
+            .map(|i| Item {
+                fact: i.fact.replace("...", "Did you know"),
+                ..i
             })
-            .filter(|e| e.starts_with("..."))
-            .map(|e| e.replace("...", "Did you know"))
-            .collect::<Vec<String>>())
+            .collect::<Vec<Item>>())
     }
 
     fn get_id(&self) -> String {
         "DYK".to_string()
     }
+
+    fn get_description(&self) -> String {
+        "The \"Did you know...\" facts recently added to Wikipedia".to_string()
+    }
+
+    fn get_url(&self) -> Option<String> {
+        Some(self.url.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::third_part::testing;
+
+    use super::*;
+
+    const EXPECTED: &[(&str, &str, &str)] = &[
+        (
+            "Did you know that the Great Molasses Flood sent a wave of molasses through the streets of Boston at about 35 miles per hour?",
+            "Great Molasses Flood",
+            "/wiki/Great_Molasses_Flood",
+        ),
+        (
+            "Did you know that mantis shrimps can strike their prey at the speed of a bullet?[1]",
+            "mantis shrimps",
+            "/wiki/Mantis_shrimp",
+        ),
+        (
+            "Did you know that the Antikythera mechanism is often described as the oldest known analogue computer?",
+            "Antikythera mechanism",
+            "/wiki/Antikythera_mechanism",
+        ),
+    ];
+
+    #[test]
+    fn test_get_facts() {
+        let url = testing::serve(include_str!("fixtures/dyk.html"), 1);
+        let client = http::Client::new(&http::Network::default(), None).unwrap();
+
+        let facts = DYK::with_url(url.clone()).get_facts(&client).unwrap();
+        let origin = url.trim_end_matches("/page");
+        assert_eq!(
+            facts,
+            EXPECTED
+                .iter()
+                .map(|(fact, title, path)| Item {
+                    fact: fact.to_string(),
+                    source_url: Some(format!("{}{}", origin, path)),
+                    title: Some(title.to_string()),
+                })
+                .collect::<Vec<Item>>()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_without_url() {
+        let crawler: Box<dyn Crawler> = toml::from_str(r#"provider = "DYK""#).unwrap();
+        assert_eq!(crawler.get_url(), Some(URL.to_string()));
+        assert_eq!(
+            toml::to_string(&crawler).unwrap().trim(),
+            r#"provider = "DYK""#
+        );
+    }
 }
@@ -0,0 +1,269 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nix::{sys::signal::kill, unistd::Pid};
+use tempfile::{tempdir, TempDir};
+
+const TIL_PAGE: &str = include_str!("../src/third_part/fixtures/til.html");
+const DYK_PAGE: &str = include_str!("../src/third_part/fixtures/dyk.html");
+
+// A home directory configured to harvest the facts from a local server
+// standing in for the providers, the daemon is killed when it is dropped.
+struct Sandbox {
+    home: TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Sandbox {
+        let url = start_provider_server();
+        let home = tempdir().unwrap();
+        let sandbox = Sandbox { home };
+        fs::create_dir_all(sandbox.get_config_dir()).unwrap();
+        fs::write(
+            sandbox.get_config_dir().join("config.toml"),
+            format!(
+                r#"template = "$fact"
+
+[[providers]]
+provider = "TIL"
+url = "{url}/til"
+
+[[providers]]
+provider = "DYK"
+url = "{url}/dyk"
+"#
+            ),
+        )
+        .unwrap();
+        sandbox
+    }
+
+    fn get_config_dir(&self) -> PathBuf {
+        self.home.path().join("cultura")
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_with_env(args, &[])
+    }
+
+    // The color variables of the environment running the tests are ignored
+    fn run_with_env(&self, args: &[&str], vars: &[(&str, &str)]) -> Output {
+        let output = Command::new(env!("CARGO_BIN_EXE_cultura"))
+            .args(args)
+            .env("HOME", self.home.path())
+            .env("CULTURA_HOME", self.get_config_dir())
+            .env("NO_PROXY", "127.0.0.1")
+            .env_remove("CLICOLOR_FORCE")
+            .env_remove("NO_COLOR")
+            .envs(vars.iter().copied())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "cultura {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    fn run_to_string(&self, args: &[&str]) -> String {
+        String::from_utf8(self.run(args).stdout).unwrap()
+    }
+
+    fn get_daemon_pid(&self) -> Option<i32> {
+        fs::read_to_string(self.get_config_dir().join("cultura.pid"))
+            .ok()
+            .and_then(|pid| pid.trim().parse().ok())
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if let Some(pid) = self.get_daemon_pid() {
+            let _ = kill(Pid::from_raw(pid), nix::sys::signal::Signal::SIGKILL);
+        }
+    }
+}
+
+fn start_provider_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let headers = BufReader::new(&stream)
+                .lines()
+                .map(|l| l.unwrap())
+                .take_while(|l| !l.is_empty())
+                .collect::<Vec<String>>();
+            let page = if headers[0].starts_with("GET /til") {
+                TIL_PAGE
+            } else {
+                DYK_PAGE
+            };
+            let _ = stream.write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .as_bytes(),
+            );
+        }
+    });
+    format!("http://{}", address)
+}
+
+// A killed daemon can be left as a zombie when nothing reaps it
+fn is_running(pid: i32) -> bool {
+    kill(Pid::from_raw(pid), None).is_ok()
+        && !fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| stat.contains(") Z "))
+            .unwrap_or(false)
+}
+
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn read_file(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+#[test]
+fn test_daemon_start_and_stop() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run(&["daemon", "start"]);
+    assert!(wait_for(|| sandbox.get_daemon_pid().is_some()));
+    let pid = sandbox.get_daemon_pid().unwrap();
+    assert!(is_running(pid));
+
+    // The daemon is started only once
+    sandbox.run(&["daemon", "start"]);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(sandbox.get_daemon_pid(), Some(pid));
+    assert!(is_running(pid));
+
+    let log_file = sandbox.get_config_dir().join("cultura.log");
+    assert!(wait_for(|| read_file(&log_file).contains("DYK: 3 found")));
+    assert!(read_file(&log_file).contains("TIL: 3 found"));
+
+    sandbox.run(&["daemon", "stop"]);
+    assert!(wait_for(|| !is_running(pid)));
+}
+
+#[test]
+fn test_daemon_rotates_the_log_file() {
+    let sandbox = Sandbox::new();
+    let log_file = sandbox.get_config_dir().join("cultura.log");
+    fs::write(&log_file, "previous harvest\n".repeat(100_000)).unwrap();
+
+    sandbox.run(&["daemon", "start"]);
+    assert!(wait_for(|| read_file(&log_file).contains("DYK: 3 found")));
+    assert!(!read_file(&log_file).contains("previous harvest"));
+    assert!(read_file(&sandbox.get_config_dir().join("cultura.log.1")).contains("previous harvest"));
+}
+
+#[test]
+fn test_harvest_and_print_facts() {
+    let sandbox = Sandbox::new();
+
+    let harvest = sandbox.run_to_string(&["fact", "harvest"]);
+    assert!(
+        harvest.contains("TIL: 3 found, 0 rejected, 0 duplicates, 3 inserted"),
+        "{}",
+        harvest
+    );
+    assert!(
+        harvest.contains("DYK: 3 found, 0 rejected, 0 duplicates, 3 inserted"),
+        "{}",
+        harvest
+    );
+
+    let facts = (0..6)
+        .map(|_| sandbox.run_to_string(&["fact", "generate-random"]))
+        .collect::<Vec<String>>();
+    assert!(facts
+        .iter()
+        .all(|f| f.contains("Today I learned") || f.contains("Did you know")));
+    assert!(facts.iter().any(|f| f.contains("Scotland")));
+    assert!(facts.iter().any(|f| f.contains("mantis shrimps")));
+
+    // Every fact has been displayed once
+    assert!(sandbox
+        .run_to_string(&["fact", "generate-random"])
+        .contains("Stay tuned for more fascinating facts soon"));
+}
+
+#[test]
+fn test_starter_pack_on_first_run() {
+    let sandbox = Sandbox::new();
+    fs::remove_file(sandbox.get_config_dir().join("config.toml")).unwrap();
+
+    // Nothing has been harvested yet, the fact comes from the starter pack
+    assert!(!sandbox
+        .run_to_string(&["fact", "generate-random"])
+        .contains("Stay tuned for more fascinating facts soon"));
+}
+
+#[test]
+fn test_doctor_reset() {
+    let sandbox = Sandbox::new();
+
+    sandbox.run(&["daemon", "start"]);
+    assert!(wait_for(|| sandbox.get_daemon_pid().is_some()));
+    let pid = sandbox.get_daemon_pid().unwrap();
+
+    // CULTURA_HOME can hold other files than the cultura ones
+    let notes = sandbox.get_config_dir().join("notes.txt");
+    fs::write(&notes, "notes").unwrap();
+
+    sandbox.run(&["doctor", "reset"]);
+    assert!(wait_for(|| !is_running(pid)));
+    assert_eq!(
+        fs::read_dir(sandbox.get_config_dir())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>(),
+        vec!["notes.txt"]
+    );
+    assert_eq!(read_file(&notes), "notes");
+}
+
+#[test]
+fn test_color_output() {
+    let sandbox = Sandbox::new();
+    let run = |args: &[&str], vars: &[(&str, &str)]| {
+        String::from_utf8(sandbox.run_with_env(args, vars).stdout).unwrap()
+    };
+    let green = "\u{1b}[32m";
+
+    // The output is piped so it is not colored by default
+    let args = ["config", "set-template", "$fact:green"];
+    run(&args, &[]);
+    assert!(!run(&["fact", "generate-random"], &[]).contains('\u{1b}'));
+    assert!(run(&["fact", "generate-random", "--color", "always"], &[]).contains(green));
+    assert!(run(&["fact", "generate-random", "--color=always"], &[]).contains(green));
+    assert!(run(&["fact", "generate-random"], &[("CLICOLOR_FORCE", "1")]).contains(green));
+    assert!(!run(
+        &["fact", "generate-random", "--color", "never"],
+        &[("CLICOLOR_FORCE", "1")]
+    )
+    .contains('\u{1b}'));
+}